@@ -0,0 +1,106 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+
+use crate::{
+    client::{
+        memory::MemoryCache,
+        redis::{RedisClient, RedisClientBuilder},
+    },
+    config::{cache::CacheBackendKind, ServiceConfig},
+};
+
+pub type CacheClient = dyn CacheBackend;
+
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn ping(&self) -> Result<Option<String>, String>;
+    async fn set(&self, key: &str, value: &str, expire: Duration) -> Result<(), String>;
+    /// Writes `value` unless the key already holds an entry whose `version` is
+    /// at least `version`. Values must be JSON objects with a numeric `version`
+    /// field. Returns whether the write happened.
+    async fn set_if_newer(
+        &self,
+        key: &str,
+        value: &str,
+        version: i64,
+        expire: Duration,
+    ) -> Result<bool, String>;
+    async fn publish(&self, channel: &str, message: &str) -> Result<(), String>;
+    /// Subscribes to `channel`. The stream ends when the subscription is lost;
+    /// messages published while nobody was subscribed are not replayed.
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, String>, String>;
+    async fn exist(&self, key: &str) -> Result<bool, String>;
+    async fn get(&self, key: &str) -> Result<Option<String>, String>;
+    async fn del(&self, key: &str) -> Result<bool, String>;
+    async fn ttl(&self, key: &str) -> Result<i64, String>;
+    /// Sliding-window counter. Forgets hits at `key` older than `window`, then
+    /// records `cost` hits at `now_ms` if that keeps the window within
+    /// `limit`. A `cost` of zero only reads the window.
+    async fn window_consume(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        cost: u64,
+        now_ms: i64,
+    ) -> Result<WindowState, String>;
+    /// Token bucket holding up to `capacity` tokens and refilling
+    /// `refill_per_ms` tokens per millisecond. Takes `cost` tokens at `now_ms`
+    /// if the bucket has them. Fails if `cost` exceeds `capacity`, since such a
+    /// request could never be granted.
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill_per_ms: f64,
+        cost: u64,
+        now_ms: i64,
+    ) -> Result<BucketState, String>;
+}
+
+/// A token bucket after a `token_bucket` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketState {
+    pub allowed: bool,
+    /// Whole tokens left.
+    pub remaining: u64,
+    /// Milliseconds until the requested tokens would be available; zero when
+    /// they were granted.
+    pub retry_after_ms: u64,
+    /// Milliseconds until the bucket is full again.
+    pub reset_ms: u64,
+}
+
+/// A sliding window after a `window_consume` call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowState {
+    /// Whether the requested hits were recorded.
+    pub allowed: bool,
+    /// Hits currently in the window.
+    pub used: u64,
+    /// Timestamp of the oldest hit in the window, in milliseconds.
+    pub oldest_ms: Option<i64>,
+}
+
+/// Reads the `version` field of a JSON cache entry written by `set_if_newer`.
+pub fn entry_version(value: &str) -> Option<i64> {
+    serde_json::from_str::<serde_json::Value>(value)
+        .ok()?
+        .get("version")?
+        .as_i64()
+}
+
+pub async fn build_from_config(config: &ServiceConfig) -> Result<Arc<CacheClient>, String> {
+    match config.cache.backend {
+        CacheBackendKind::Redis => {
+            let client = RedisClient::build_from_config(config)
+                .await
+                .map_err(|e| format!("Error in building redis client: {}", e))?;
+            Ok(Arc::new(client))
+        }
+        CacheBackendKind::Memory => Ok(MemoryCache::new()),
+    }
+}
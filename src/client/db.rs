@@ -1,5 +1,3 @@
-use std::time::Duration;
-
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
 use crate::config::ServiceConfig;
@@ -10,20 +8,41 @@ pub trait DatabaseClientExt: Sized {
     fn build_from_config(
         config: &ServiceConfig,
     ) -> impl std::future::Future<Output = Result<DatabaseConnection, String>>;
+
+    /// Connects to the read replica, or returns `None` when none is configured.
+    fn build_read_replica_from_config(
+        config: &ServiceConfig,
+    ) -> impl std::future::Future<Output = Result<Option<DatabaseConnection>, String>>;
+}
+
+fn connect_options(config: &ServiceConfig, url: String) -> ConnectOptions {
+    let mut opt = ConnectOptions::new(url);
+    opt.max_connections(config.db.max_connections)
+        .min_connections(config.db.min_connections)
+        .connect_timeout(config.db.connect_timeout)
+        .acquire_timeout(config.db.acquire_timeout)
+        .idle_timeout(config.db.idle_timeout)
+        .max_lifetime(config.db.max_lifetime);
+    opt
 }
 
 impl DatabaseClientExt for DatabaseClient {
     async fn build_from_config(config: &ServiceConfig) -> Result<DatabaseConnection, String> {
-        let mut opt = ConnectOptions::new(config.db.get_url());
-        opt.max_connections(100)
-            .min_connections(5)
-            .connect_timeout(Duration::from_secs(8))
-            .acquire_timeout(Duration::from_secs(8))
-            .idle_timeout(Duration::from_secs(8))
-            .max_lifetime(Duration::from_secs(8));
-        let db = Database::connect(opt)
+        let db = Database::connect(connect_options(config, config.db.get_url()))
             .await
             .map_err(|e| format!("Error in connectiong to database: {}", e))?;
         Ok(db)
     }
+
+    async fn build_read_replica_from_config(
+        config: &ServiceConfig,
+    ) -> Result<Option<DatabaseConnection>, String> {
+        let Some(url) = config.db.get_read_replica_url() else {
+            return Ok(None);
+        };
+        let db = Database::connect(connect_options(config, url))
+            .await
+            .map_err(|e| format!("Error in connectiong to read replica: {}", e))?;
+        Ok(Some(db))
+    }
 }
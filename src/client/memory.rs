@@ -0,0 +1,522 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use tracing::info;
+
+use crate::client::cache::{entry_version, BucketState, CacheBackend, WindowState};
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct Entry {
+    value: String,
+    expires_at: Instant,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// Hit timestamps of one sliding window, oldest first.
+#[derive(Debug, Default)]
+struct Window {
+    hits: VecDeque<i64>,
+    expires_at: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_ms: i64,
+    expires_at: Instant,
+}
+
+/// In-process cache with per-key TTL, used when no Redis is available.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, Entry>>,
+    windows: Mutex<HashMap<String, Window>>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Arc<Self> {
+        let cache = Arc::new(Self::default());
+        let weak = Arc::downgrade(&cache);
+        tokio::spawn(async move { Self::sweep(weak).await });
+        cache
+    }
+
+    async fn sweep(cache: Weak<Self>) {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            let Some(cache) = cache.upgrade() else {
+                break;
+            };
+            let now = Instant::now();
+            cache
+                .entries
+                .lock()
+                .unwrap()
+                .retain(|_, entry| !entry.is_expired(now));
+            cache
+                .windows
+                .lock()
+                .unwrap()
+                .retain(|_, window| window.expires_at.is_some_and(|at| at > now));
+            cache
+                .buckets
+                .lock()
+                .unwrap()
+                .retain(|_, bucket| bucket.expires_at > now);
+        }
+    }
+
+    /// Drops every entry.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+        self.windows.lock().unwrap().clear();
+        self.buckets.lock().unwrap().clear();
+    }
+
+    fn live_entry(&self, key: &str) -> Option<Entry> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.is_expired(now) => {
+                entries.remove(key);
+                None
+            }
+            Some(entry) => Some(entry.clone()),
+            None => None,
+        }
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn ping(&self) -> Result<Option<String>, String> {
+        info!("ping memory cache");
+        Ok(Some("PONG".to_string()))
+    }
+
+    async fn set(&self, key: &str, value: &str, expire: Duration) -> Result<(), String> {
+        let entry = Entry {
+            value: value.to_string(),
+            expires_at: Instant::now() + expire,
+        };
+        self.entries.lock().unwrap().insert(key.to_string(), entry);
+        info!("set key memory cache: {key}");
+        Ok(())
+    }
+
+    async fn set_if_newer(
+        &self,
+        key: &str,
+        value: &str,
+        version: i64,
+        expire: Duration,
+    ) -> Result<bool, String> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        let is_stale = entries
+            .get(key)
+            .filter(|entry| !entry.is_expired(now))
+            .and_then(|entry| entry_version(&entry.value))
+            .is_some_and(|current| current >= version);
+        if is_stale {
+            info!("skip stale write to memory cache: {key}");
+            return Ok(false);
+        }
+        entries.insert(
+            key.to_string(),
+            Entry {
+                value: value.to_string(),
+                expires_at: now + expire,
+            },
+        );
+        info!("set versioned key memory cache: {key}");
+        Ok(true)
+    }
+
+    async fn publish(&self, channel: &str, _message: &str) -> Result<(), String> {
+        // A memory cache lives in a single process, so there is nobody to notify.
+        info!("publish to {channel}: memory cache has no subscribers");
+        Ok(())
+    }
+
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, String>, String> {
+        info!("subscribed to {channel}");
+        Ok(stream::pending().boxed())
+    }
+
+    async fn exist(&self, key: &str) -> Result<bool, String> {
+        info!("check key exists: {key}");
+        Ok(self.live_entry(key).is_some())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        info!("get value: {key}");
+        Ok(self.live_entry(key).map(|entry| entry.value))
+    }
+
+    async fn del(&self, key: &str) -> Result<bool, String> {
+        let now = Instant::now();
+        let removed = self.entries.lock().unwrap().remove(key);
+        info!("delete value: {key}");
+        Ok(removed.is_some_and(|entry| !entry.is_expired(now)))
+    }
+
+    async fn ttl(&self, key: &str) -> Result<i64, String> {
+        info!("get TTL value: {key}");
+        Ok(match self.live_entry(key) {
+            Some(entry) => entry
+                .expires_at
+                .saturating_duration_since(Instant::now())
+                .as_secs() as i64,
+            None => -2,
+        })
+    }
+
+    async fn window_consume(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        cost: u64,
+        now_ms: i64,
+    ) -> Result<WindowState, String> {
+        let mut windows = self.windows.lock().unwrap();
+        let entry = windows.entry(key.to_string()).or_default();
+        let cutoff = now_ms - window.as_millis() as i64;
+        while entry.hits.front().is_some_and(|hit| *hit <= cutoff) {
+            entry.hits.pop_front();
+        }
+        let allowed = entry.hits.len() as u64 + cost <= limit;
+        if allowed && cost > 0 {
+            entry
+                .hits
+                .extend(std::iter::repeat_n(now_ms, cost as usize));
+            entry.expires_at = Some(Instant::now() + window);
+        }
+        info!("consume window {key}: allowed={allowed}");
+        Ok(WindowState {
+            allowed,
+            used: entry.hits.len() as u64,
+            oldest_ms: entry.hits.front().copied(),
+        })
+    }
+
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill_per_ms: f64,
+        cost: u64,
+        now_ms: i64,
+    ) -> Result<BucketState, String> {
+        if cost > capacity {
+            return Err(format!(
+                "Token bucket {key} cost {cost} exceeds its capacity {capacity}"
+            ));
+        }
+        let now = Instant::now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let capacity = capacity as f64;
+        let cost = cost as f64;
+        let mut tokens = match buckets.get(key).filter(|bucket| bucket.expires_at > now) {
+            Some(bucket) => (bucket.tokens
+                + (now_ms - bucket.updated_ms).max(0) as f64 * refill_per_ms)
+                .min(capacity),
+            None => capacity,
+        };
+        let allowed = tokens >= cost;
+        let retry_after_ms = if allowed {
+            tokens -= cost;
+            0
+        } else {
+            ((cost - tokens) / refill_per_ms).ceil() as u64
+        };
+        let reset_ms = ((capacity - tokens) / refill_per_ms).ceil() as u64;
+        buckets.insert(
+            key.to_string(),
+            Bucket {
+                tokens,
+                updated_ms: now_ms,
+                expires_at: now + Duration::from_millis(reset_ms.max(1)),
+            },
+        );
+        info!("take from token bucket {key}: allowed={allowed}");
+        Ok(BucketState {
+            allowed,
+            remaining: tokens.floor() as u64,
+            retry_after_ms,
+            reset_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: Duration = Duration::from_secs(60);
+
+    #[tokio::test]
+    async fn set_then_get_returns_value() {
+        let cache = MemoryCache::default();
+        cache.set("key", "value", MINUTE).await.unwrap();
+
+        assert_eq!(cache.get("key").await.unwrap().as_deref(), Some("value"));
+        assert!(cache.exist("key").await.unwrap());
+        assert_eq!(cache.get("other").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn entries_expire_after_ttl() {
+        let cache = MemoryCache::default();
+        cache
+            .set("key", "value", Duration::from_millis(20))
+            .await
+            .unwrap();
+        assert!(cache.ttl("key").await.unwrap() >= 0);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        assert_eq!(cache.get("key").await.unwrap(), None);
+        assert!(!cache.exist("key").await.unwrap());
+        assert_eq!(cache.ttl("key").await.unwrap(), -2);
+        assert!(!cache.del("key").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn del_removes_live_entry() {
+        let cache = MemoryCache::default();
+        cache.set("key", "value", MINUTE).await.unwrap();
+
+        assert!(cache.del("key").await.unwrap());
+        assert_eq!(cache.get("key").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn set_if_newer_skips_stale_versions() {
+        let cache = MemoryCache::default();
+        assert!(cache
+            .set_if_newer("key", r#"{"version":2}"#, 2, MINUTE)
+            .await
+            .unwrap());
+
+        assert!(!cache
+            .set_if_newer("key", r#"{"version":1}"#, 1, MINUTE)
+            .await
+            .unwrap());
+        assert!(!cache
+            .set_if_newer("key", r#"{"version":2}"#, 2, MINUTE)
+            .await
+            .unwrap());
+        assert_eq!(
+            cache.get("key").await.unwrap().as_deref(),
+            Some(r#"{"version":2}"#)
+        );
+
+        assert!(cache
+            .set_if_newer("key", r#"{"version":3}"#, 3, MINUTE)
+            .await
+            .unwrap());
+        assert_eq!(
+            cache.get("key").await.unwrap().as_deref(),
+            Some(r#"{"version":3}"#)
+        );
+    }
+
+    #[tokio::test]
+    async fn set_if_newer_replaces_expired_entry() {
+        let cache = MemoryCache::default();
+        cache
+            .set_if_newer("key", r#"{"version":5}"#, 5, Duration::from_millis(20))
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(40)).await;
+
+        assert!(cache
+            .set_if_newer("key", r#"{"version":1}"#, 1, MINUTE)
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn window_consume_rejects_over_limit() {
+        let cache = MemoryCache::default();
+        for used in 1..=3 {
+            let state = cache
+                .window_consume("w", 3, MINUTE, 1, 1_000)
+                .await
+                .unwrap();
+            assert!(state.allowed);
+            assert_eq!(state.used, used);
+        }
+
+        let state = cache
+            .window_consume("w", 3, MINUTE, 1, 1_001)
+            .await
+            .unwrap();
+        assert!(!state.allowed);
+        assert_eq!(state.used, 3);
+        assert_eq!(state.oldest_ms, Some(1_000));
+    }
+
+    #[tokio::test]
+    async fn window_consume_frees_capacity_at_the_window_edge() {
+        let cache = MemoryCache::default();
+        cache
+            .window_consume("w", 2, MINUTE, 1, 1_000)
+            .await
+            .unwrap();
+        cache
+            .window_consume("w", 2, MINUTE, 1, 2_000)
+            .await
+            .unwrap();
+
+        // The first hit is still inside the window one millisecond early.
+        let state = cache
+            .window_consume("w", 2, MINUTE, 1, 60_999)
+            .await
+            .unwrap();
+        assert!(!state.allowed);
+        assert_eq!(state.oldest_ms, Some(1_000));
+
+        // Exactly one window later it has dropped out.
+        let state = cache
+            .window_consume("w", 2, MINUTE, 1, 61_000)
+            .await
+            .unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.used, 2);
+        assert_eq!(state.oldest_ms, Some(2_000));
+    }
+
+    #[tokio::test]
+    async fn window_consume_rejects_without_partial_use() {
+        let cache = MemoryCache::default();
+        let state = cache.window_consume("w", 3, MINUTE, 2, 0).await.unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.used, 2);
+
+        let state = cache.window_consume("w", 3, MINUTE, 2, 10).await.unwrap();
+        assert!(!state.allowed);
+        assert_eq!(state.used, 2);
+
+        let state = cache.window_consume("w", 3, MINUTE, 1, 20).await.unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.used, 3);
+    }
+
+    #[tokio::test]
+    async fn window_consume_zero_cost_only_reads() {
+        let cache = MemoryCache::default();
+        let state = cache.window_consume("w", 1, MINUTE, 0, 0).await.unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.used, 0);
+        assert_eq!(state.oldest_ms, None);
+
+        cache.window_consume("w", 1, MINUTE, 1, 5).await.unwrap();
+        let state = cache.window_consume("w", 1, MINUTE, 0, 10).await.unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.used, 1);
+        assert_eq!(state.oldest_ms, Some(5));
+
+        let state = cache
+            .window_consume("w", 1, MINUTE, 0, 60_005)
+            .await
+            .unwrap();
+        assert_eq!(state.used, 0);
+        assert_eq!(state.oldest_ms, None);
+    }
+
+    #[tokio::test]
+    async fn window_consume_keeps_keys_apart() {
+        let cache = MemoryCache::default();
+        cache.window_consume("a", 1, MINUTE, 1, 0).await.unwrap();
+        let state = cache.window_consume("b", 1, MINUTE, 1, 0).await.unwrap();
+        assert!(state.allowed);
+        assert!(
+            !cache
+                .window_consume("a", 1, MINUTE, 1, 0)
+                .await
+                .unwrap()
+                .allowed
+        );
+    }
+
+    #[tokio::test]
+    async fn token_bucket_drains_and_refills() {
+        let cache = MemoryCache::default();
+        // One token every 100 ms.
+        let refill_per_ms = 0.01;
+
+        let state = cache
+            .token_bucket("b", 2, refill_per_ms, 1, 0)
+            .await
+            .unwrap();
+        assert_eq!(
+            state,
+            BucketState {
+                allowed: true,
+                remaining: 1,
+                retry_after_ms: 0,
+                reset_ms: 100,
+            }
+        );
+        let state = cache
+            .token_bucket("b", 2, refill_per_ms, 1, 0)
+            .await
+            .unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.remaining, 0);
+        assert_eq!(state.reset_ms, 200);
+
+        let state = cache
+            .token_bucket("b", 2, refill_per_ms, 1, 50)
+            .await
+            .unwrap();
+        assert!(!state.allowed);
+        assert_eq!(state.retry_after_ms, 50);
+
+        let state = cache
+            .token_bucket("b", 2, refill_per_ms, 1, 100)
+            .await
+            .unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn token_bucket_never_exceeds_capacity() {
+        let cache = MemoryCache::default();
+        cache.token_bucket("b", 2, 0.01, 2, 0).await.unwrap();
+
+        let state = cache
+            .token_bucket("b", 2, 0.01, 0, 1_000_000)
+            .await
+            .unwrap();
+        assert_eq!(state.remaining, 2);
+        assert_eq!(state.reset_ms, 0);
+    }
+
+    #[tokio::test]
+    async fn token_bucket_rejects_cost_above_capacity() {
+        let cache = MemoryCache::default();
+
+        assert!(cache.token_bucket("b", 2, 0.01, 3, 0).await.is_err());
+
+        let state = cache.token_bucket("b", 2, 0.01, 2, 0).await.unwrap();
+        assert!(state.allowed);
+        assert_eq!(state.remaining, 0);
+    }
+}
@@ -1,2 +1,5 @@
+pub mod cache;
 pub mod db;
+pub mod memory;
 pub mod redis;
+pub mod telegram;
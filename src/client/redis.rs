@@ -1,84 +1,531 @@
-use redis::{Client, RedisError};
-use std::time::Duration;
-use tracing::info;
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use redis::{
+    aio::{ConnectionManager, ConnectionManagerConfig, PubSub},
+    cluster::ClusterClient,
+    cluster_async::ClusterConnection,
+    sentinel::{Sentinel, SentinelNodeConnectionInfo},
+    Cmd, ErrorKind, FromRedisValue, ProtocolVersion, RedisConnectionInfo, RedisError, RedisResult,
+    TlsCertificates, TlsMode,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+use uuid::Uuid;
 
-use crate::config::ServiceConfig;
+use crate::{
+    client::cache::{BucketState, CacheBackend, WindowState},
+    config::{
+        redis::{RedisConfig, RedisTopology},
+        ServiceConfig,
+    },
+};
 
-pub type RedisClient = redis::Client;
-pub trait RedisClientBuilder: Sized {
-    fn build_from_config(config: &ServiceConfig) -> Result<Self, RedisError>;
+/// Shared, auto-reconnecting connection to Redis.
+///
+/// Cloning is cheap: every clone talks over the same underlying connection.
+#[derive(Clone)]
+pub struct RedisClient {
+    connection: RedisConnection,
+    /// Nodes to subscribe on, tried in order until one accepts. Sentinel
+    /// deployments resolve the current master instead, so this is empty for
+    /// them.
+    pubsub_clients: Vec<redis::Client>,
 }
 
-pub trait RedisClientExt: RedisClientBuilder {
-    fn ping(&self) -> impl std::future::Future<Output = Result<Option<String>, RedisError>>;
-    fn set(
-        &self,
-        key: &str,
-        value: &str,
-        expire: Duration,
-    ) -> impl std::future::Future<Output = Result<(), RedisError>>;
-    fn exist(&self, key: &str) -> impl std::future::Future<Output = Result<bool, RedisError>>;
-    fn get(
-        &self,
-        key: &str,
-    ) -> impl std::future::Future<Output = Result<Option<String>, RedisError>>;
-    fn del(&self, key: &str) -> impl std::future::Future<Output = Result<bool, RedisError>>;
-    fn ttl(&self, key: &str) -> impl std::future::Future<Output = Result<i64, RedisError>>;
+#[derive(Clone)]
+enum RedisConnection {
+    Standalone(Box<ConnectionManager>),
+    Sentinel(Arc<SentinelConnection>),
+    Cluster(ClusterConnection),
+}
+
+/// Connection to the master of a sentinel-managed deployment.
+///
+/// The master address is re-resolved through the sentinels whenever a command
+/// fails in a way that suggests a failover happened.
+struct SentinelConnection {
+    sentinel: Mutex<Sentinel>,
+    master_name: String,
+    node_info: SentinelNodeConnectionInfo,
+    manager_config: ConnectionManagerConfig,
+    manager: RwLock<ConnectionManager>,
+}
+
+impl SentinelConnection {
+    async fn connect(
+        sentinel: &mut Sentinel,
+        master_name: &str,
+        node_info: &SentinelNodeConnectionInfo,
+        manager_config: &ConnectionManagerConfig,
+    ) -> RedisResult<ConnectionManager> {
+        let client = sentinel
+            .async_master_for(master_name, Some(node_info))
+            .await?;
+        ConnectionManager::new_with_config(client, manager_config.clone()).await
+    }
+
+    async fn query<T: FromRedisValue>(&self, cmd: &Cmd) -> RedisResult<T> {
+        let mut conn = self.manager.read().await.clone();
+        match cmd.query_async::<T>(&mut conn).await {
+            Err(e) if Self::is_failover(&e) => {
+                warn!("Redis master unreachable ({e}), re-resolving through sentinel");
+                let mut conn = self.reresolve().await?;
+                cmd.query_async::<T>(&mut conn).await
+            }
+            result => result,
+        }
+    }
+
+    async fn master_client(&self) -> RedisResult<redis::Client> {
+        let mut sentinel = self.sentinel.lock().await;
+        sentinel
+            .async_master_for(&self.master_name, Some(&self.node_info))
+            .await
+    }
+
+    async fn reresolve(&self) -> RedisResult<ConnectionManager> {
+        let mut sentinel = self.sentinel.lock().await;
+        let manager = Self::connect(
+            &mut sentinel,
+            &self.master_name,
+            &self.node_info,
+            &self.manager_config,
+        )
+        .await?;
+        *self.manager.write().await = manager.clone();
+        Ok(manager)
+    }
+
+    fn is_failover(e: &RedisError) -> bool {
+        e.kind() == ErrorKind::ReadOnly
+            || e.is_io_error()
+            || e.is_connection_dropped()
+            || e.is_connection_refusal()
+            || e.is_timeout()
+    }
+}
+
+pub trait RedisClientBuilder: Sized {
+    fn build_from_config(
+        config: &ServiceConfig,
+    ) -> impl std::future::Future<Output = Result<Self, RedisError>>;
 }
 
 impl RedisClientBuilder for RedisClient {
-    fn build_from_config(config: &ServiceConfig) -> Result<Self, RedisError> {
-        Ok(redis::Client::open(config.redis.get_url())?)
+    async fn build_from_config(config: &ServiceConfig) -> Result<Self, RedisError> {
+        let redis = &config.redis;
+        match redis.topology {
+            RedisTopology::Standalone => {
+                let client = Self::node_client(redis, redis.get_url())?;
+                let manager =
+                    ConnectionManager::new_with_config(client.clone(), Self::manager_config(redis))
+                        .await?;
+                Ok(Self {
+                    connection: RedisConnection::Standalone(Box::new(manager)),
+                    pubsub_clients: vec![client],
+                })
+            }
+            RedisTopology::Sentinel => Ok(Self {
+                connection: RedisConnection::Sentinel(Arc::new(
+                    Self::sentinel_connection(redis).await?,
+                )),
+                pubsub_clients: Vec::new(),
+            }),
+            RedisTopology::Cluster => {
+                // PUBLISH is broadcast to every node of a cluster, so subscribing
+                // on any single reachable seed node sees all messages.
+                let pubsub_clients = redis
+                    .get_cluster_urls()
+                    .map_err(|e| Self::config_error("Invalid REDIS_CLUSTER_NODES", e))?
+                    .into_iter()
+                    .map(|url| Self::node_client(redis, url))
+                    .collect::<RedisResult<_>>()?;
+                Ok(Self {
+                    connection: RedisConnection::Cluster(Self::cluster_connection(redis).await?),
+                    pubsub_clients,
+                })
+            }
+        }
+    }
+}
+
+impl RedisClient {
+    fn config_error(description: &'static str, detail: String) -> RedisError {
+        RedisError::from((ErrorKind::InvalidClientConfig, description, detail))
+    }
+
+    fn root_cert(redis: &RedisConfig) -> RedisResult<Option<Vec<u8>>> {
+        redis
+            .ca_file
+            .as_ref()
+            .map(|ca_file| {
+                std::fs::read(ca_file).map_err(|e| {
+                    RedisError::from((
+                        ErrorKind::IoError,
+                        "Failed to read REDIS_CA_FILE",
+                        e.to_string(),
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    fn manager_config(redis: &RedisConfig) -> ConnectionManagerConfig {
+        ConnectionManagerConfig::new()
+            .set_connection_timeout(redis.connect_timeout)
+            .set_response_timeout(redis.response_timeout)
+            .set_number_of_retries(redis.reconnect_retries)
+            .set_factor(redis.reconnect_factor_ms)
+            .set_exponent_base(redis.reconnect_exponent_base)
+            .set_max_delay(redis.reconnect_max_delay_ms)
+    }
+
+    fn node_client(redis: &RedisConfig, url: String) -> RedisResult<redis::Client> {
+        match Self::root_cert(redis)? {
+            Some(root_cert) => redis::Client::build_with_tls(
+                url,
+                TlsCertificates {
+                    client_tls: None,
+                    root_cert: Some(root_cert),
+                },
+            ),
+            None => redis::Client::open(url),
+        }
+    }
+
+    async fn sentinel_connection(redis: &RedisConfig) -> RedisResult<SentinelConnection> {
+        if redis.ca_file.is_some() {
+            return Err(Self::config_error(
+                "Unsupported sentinel configuration",
+                "REDIS_CA_FILE is not supported with the sentinel topology".to_string(),
+            ));
+        }
+        let sentinel_urls = redis
+            .get_sentinel_urls()
+            .map_err(|e| Self::config_error("Invalid REDIS_SENTINEL_NODES", e))?;
+        let mut sentinel = Sentinel::build(sentinel_urls)?;
+        let node_info = SentinelNodeConnectionInfo {
+            tls_mode: redis.tls.then_some(TlsMode::Secure),
+            redis_connection_info: Some(RedisConnectionInfo {
+                db: redis.database,
+                username: redis.username.clone(),
+                password: redis.password.clone(),
+                protocol: ProtocolVersion::RESP2,
+            }),
+        };
+        let manager_config = Self::manager_config(redis);
+        let manager = SentinelConnection::connect(
+            &mut sentinel,
+            &redis.sentinel_master,
+            &node_info,
+            &manager_config,
+        )
+        .await?;
+        Ok(SentinelConnection {
+            sentinel: Mutex::new(sentinel),
+            master_name: redis.sentinel_master.clone(),
+            node_info,
+            manager_config,
+            manager: RwLock::new(manager),
+        })
+    }
+
+    async fn cluster_connection(redis: &RedisConfig) -> RedisResult<ClusterConnection> {
+        let cluster_urls = redis
+            .get_cluster_urls()
+            .map_err(|e| Self::config_error("Invalid REDIS_CLUSTER_NODES", e))?;
+        let mut builder = ClusterClient::builder(cluster_urls)
+            .connection_timeout(redis.connect_timeout)
+            .response_timeout(redis.response_timeout)
+            .retries(redis.reconnect_retries as u32)
+            .retry_wait_formula(redis.reconnect_factor_ms, redis.reconnect_exponent_base)
+            .max_retry_wait(redis.reconnect_max_delay_ms);
+        if let Some(root_cert) = Self::root_cert(redis)? {
+            builder = builder.certs(TlsCertificates {
+                client_tls: None,
+                root_cert: Some(root_cert),
+            });
+        }
+        builder.build()?.get_async_connection().await
+    }
+
+    /// Pub/sub connection subscribed to `channel` on the first node that
+    /// accepts it. Nodes that fail are logged and skipped; the last error is
+    /// returned if none do.
+    async fn pubsub(&self, channel: &str) -> RedisResult<PubSub> {
+        if let RedisConnection::Sentinel(sentinel) = &self.connection {
+            return Self::subscribed(&sentinel.master_client().await?, channel).await;
+        }
+        let mut last_error =
+            Self::config_error("Pub/sub unavailable", "no node to subscribe on".to_string());
+        for client in &self.pubsub_clients {
+            match Self::subscribed(client, channel).await {
+                Ok(pubsub) => return Ok(pubsub),
+                Err(e) => {
+                    metrics::counter!("redis_pubsub_node_failures_total").increment(1);
+                    warn!(
+                        "Failed to subscribe on {}: {}",
+                        client.get_connection_info().addr,
+                        e
+                    );
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    async fn subscribed(client: &redis::Client, channel: &str) -> RedisResult<PubSub> {
+        let mut pubsub = client.get_async_pubsub().await?;
+        pubsub.subscribe(channel).await?;
+        Ok(pubsub)
+    }
+
+    async fn query<T: FromRedisValue>(
+        &self,
+        command: &'static str,
+        cmd: &Cmd,
+    ) -> Result<T, String> {
+        let started = Instant::now();
+        let result = match &self.connection {
+            RedisConnection::Standalone(manager) => {
+                cmd.query_async::<T>(&mut manager.as_ref().clone()).await
+            }
+            RedisConnection::Sentinel(sentinel) => sentinel.query::<T>(cmd).await,
+            RedisConnection::Cluster(conn) => cmd.query_async::<T>(&mut conn.clone()).await,
+        };
+
+        metrics::counter!("redis_commands_total", "command" => command).increment(1);
+        metrics::histogram!("redis_command_duration_seconds", "command" => command)
+            .record(started.elapsed().as_secs_f64());
+        result.map_err(|e| {
+            metrics::counter!("redis_errors_total", "command" => command, "kind" => format!("{:?}", e.kind()))
+                .increment(1);
+            e.to_string()
+        })
     }
 }
 
-impl RedisClientExt for Client {
-    async fn ping(&self) -> Result<Option<String>, RedisError> {
-        let mut conn = self.get_multiplexed_async_connection().await?;
-        let value: Option<String> = redis::cmd("PING").query_async(&mut conn).await?;
+/// Compare-and-set on the `version` field of a JSON entry. Entries that can't
+/// be decoded, or carry no version, are overwritten.
+const SET_IF_NEWER_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current then
+    local ok, decoded = pcall(cjson.decode, current)
+    if ok and type(decoded) == 'table' and tonumber(decoded['version']) ~= nil
+        and tonumber(decoded['version']) >= tonumber(ARGV[2]) then
+        return 0
+    end
+end
+redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[3])
+return 1
+"#;
+
+/// Sliding window over a sorted set of hit timestamps. Members carry a
+/// per-call nonce so hits in the same millisecond don't collapse.
+const WINDOW_CONSUME_SCRIPT: &str = r#"
+local now = tonumber(ARGV[4])
+local window = tonumber(ARGV[2])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - window)
+local used = redis.call('ZCARD', KEYS[1])
+local cost = tonumber(ARGV[3])
+local allowed = 0
+if used + cost <= tonumber(ARGV[1]) then
+    allowed = 1
+    for i = 1, cost do
+        redis.call('ZADD', KEYS[1], now, ARGV[5] .. ':' .. i)
+    end
+    used = used + cost
+    if cost > 0 then
+        redis.call('PEXPIRE', KEYS[1], window)
+    end
+end
+local oldest = redis.call('ZRANGE', KEYS[1], 0, 0, 'WITHSCORES')
+local oldest_ms = -1
+if oldest[2] then
+    oldest_ms = tonumber(oldest[2])
+end
+return {allowed, used, oldest_ms}
+"#;
+
+/// Token bucket stored as a hash of the token count and when it was last
+/// refilled; the key expires once the bucket would be full again.
+const TOKEN_BUCKET_SCRIPT: &str = r#"
+local capacity = tonumber(ARGV[1])
+local rate = tonumber(ARGV[2])
+local cost = tonumber(ARGV[3])
+local now = tonumber(ARGV[4])
+local bucket = redis.call('HMGET', KEYS[1], 'tokens', 'ts')
+local tokens = tonumber(bucket[1]) or capacity
+local ts = tonumber(bucket[2]) or now
+tokens = math.min(capacity, tokens + math.max(0, now - ts) * rate)
+local allowed = 0
+local retry = 0
+if tokens >= cost then
+    tokens = tokens - cost
+    allowed = 1
+else
+    retry = math.ceil((cost - tokens) / rate)
+end
+local reset = math.ceil((capacity - tokens) / rate)
+redis.call('HSET', KEYS[1], 'tokens', tostring(tokens), 'ts', now)
+redis.call('PEXPIRE', KEYS[1], math.max(reset, 1))
+return {allowed, math.floor(tokens), retry, reset}
+"#;
+
+#[async_trait]
+impl CacheBackend for RedisClient {
+    async fn ping(&self) -> Result<Option<String>, String> {
+        let value: Option<String> = self.query("PING", &redis::cmd("PING")).await?;
         info!("ping redis server");
         Ok(value)
     }
 
-    async fn set(&self, key: &str, value: &str, expire: Duration) -> Result<(), RedisError> {
-        let mut conn = self.get_multiplexed_async_connection().await?;
-        let msg: String = redis::cmd("SET")
-            .arg(&[key, value])
-            .query_async(&mut conn)
+    async fn set(&self, key: &str, value: &str, expire: Duration) -> Result<(), String> {
+        let msg: String = self
+            .query(
+                "SET",
+                redis::cmd("SET")
+                    .arg(&[key, value])
+                    .arg("EX")
+                    .arg(expire.as_secs().max(1)),
+            )
             .await?;
         info!("set key redis: {msg}");
-        let msg: i32 = redis::cmd("EXPIRE")
-            .arg(&[key, &expire.as_secs().to_string()])
-            .query_async(&mut conn)
+        Ok(())
+    }
+
+    async fn set_if_newer(
+        &self,
+        key: &str,
+        value: &str,
+        version: i64,
+        expire: Duration,
+    ) -> Result<bool, String> {
+        let written: bool = self
+            .query(
+                "EVAL",
+                redis::cmd("EVAL")
+                    .arg(SET_IF_NEWER_SCRIPT)
+                    .arg(1)
+                    .arg(key)
+                    .arg(value)
+                    .arg(version)
+                    .arg(expire.as_secs().max(1)),
+            )
+            .await?;
+        info!("set versioned key redis: {key} written={written}");
+        Ok(written)
+    }
+
+    async fn publish(&self, channel: &str, message: &str) -> Result<(), String> {
+        let receivers: i64 = self
+            .query("PUBLISH", redis::cmd("PUBLISH").arg(channel).arg(message))
             .await?;
-        info!("set expire time redis: {msg}");
+        info!("publish to {channel}: {receivers} receivers");
         Ok(())
     }
 
-    async fn exist(&self, key: &str) -> Result<bool, RedisError> {
-        let mut conn = self.get_multiplexed_async_connection().await?;
-        let value: bool = redis::cmd("EXISTS").arg(key).query_async(&mut conn).await?;
+    async fn subscribe(&self, channel: &str) -> Result<BoxStream<'static, String>, String> {
+        let pubsub = self.pubsub(channel).await.map_err(|e| {
+            metrics::counter!("redis_errors_total", "command" => "SUBSCRIBE", "kind" => format!("{:?}", e.kind()))
+                .increment(1);
+            e.to_string()
+        })?;
+        info!("subscribed to {channel}");
+        Ok(pubsub
+            .into_on_message()
+            .filter_map(|msg| async move { msg.get_payload::<String>().ok() })
+            .boxed())
+    }
+
+    async fn exist(&self, key: &str) -> Result<bool, String> {
+        let value: bool = self.query("EXISTS", redis::cmd("EXISTS").arg(key)).await?;
         info!("check key exists: {key}");
         Ok(value)
     }
 
-    async fn get(&self, key: &str) -> Result<Option<String>, RedisError> {
-        let mut conn = self.get_multiplexed_async_connection().await?;
-        let value: Option<String> = redis::cmd("GET").arg(key).query_async(&mut conn).await?;
+    async fn get(&self, key: &str) -> Result<Option<String>, String> {
+        let value: Option<String> = self.query("GET", redis::cmd("GET").arg(key)).await?;
         info!("get value: {key}");
         Ok(value)
     }
 
-    async fn del(&self, key: &str) -> Result<bool, RedisError> {
-        let mut conn = self.get_multiplexed_async_connection().await?;
-        let value: i32 = redis::cmd("DEL").arg(key).query_async(&mut conn).await?;
+    async fn del(&self, key: &str) -> Result<bool, String> {
+        let value: i32 = self.query("DEL", redis::cmd("DEL").arg(key)).await?;
         info!("delete value: {key}");
         Ok(value == 1)
     }
-    async fn ttl(&self, key: &str) -> Result<i64, RedisError> {
-        let mut conn = self.get_multiplexed_async_connection().await?;
-        let value: i64 = redis::cmd("TTL").arg(key).query_async(&mut conn).await?;
+    async fn ttl(&self, key: &str) -> Result<i64, String> {
+        let value: i64 = self.query("TTL", redis::cmd("TTL").arg(key)).await?;
         info!("get TTL value: {key}");
         Ok(value)
     }
+
+    async fn window_consume(
+        &self,
+        key: &str,
+        limit: u64,
+        window: Duration,
+        cost: u64,
+        now_ms: i64,
+    ) -> Result<WindowState, String> {
+        let (allowed, used, oldest_ms): (bool, u64, i64) = self
+            .query(
+                "EVAL",
+                redis::cmd("EVAL")
+                    .arg(WINDOW_CONSUME_SCRIPT)
+                    .arg(1)
+                    .arg(key)
+                    .arg(limit)
+                    .arg(window.as_millis() as u64)
+                    .arg(cost)
+                    .arg(now_ms)
+                    .arg(Uuid::new_v4().to_string()),
+            )
+            .await?;
+        info!("consume window {key}: allowed={allowed} used={used}");
+        Ok(WindowState {
+            allowed,
+            used,
+            oldest_ms: (oldest_ms >= 0).then_some(oldest_ms),
+        })
+    }
+
+    async fn token_bucket(
+        &self,
+        key: &str,
+        capacity: u64,
+        refill_per_ms: f64,
+        cost: u64,
+        now_ms: i64,
+    ) -> Result<BucketState, String> {
+        if cost > capacity {
+            return Err(format!(
+                "Token bucket {key} cost {cost} exceeds its capacity {capacity}"
+            ));
+        }
+        let (allowed, remaining, retry_after_ms, reset_ms): (bool, u64, u64, u64) = self
+            .query(
+                "EVAL",
+                redis::cmd("EVAL")
+                    .arg(TOKEN_BUCKET_SCRIPT)
+                    .arg(1)
+                    .arg(key)
+                    .arg(capacity)
+                    .arg(refill_per_ms)
+                    .arg(cost)
+                    .arg(now_ms),
+            )
+            .await?;
+        info!("take from token bucket {key}: allowed={allowed} remaining={remaining}");
+        Ok(BucketState {
+            allowed,
+            remaining,
+            retry_after_ms,
+            reset_ms,
+        })
+    }
 }
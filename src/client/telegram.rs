@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::info;
+
+use crate::config::ServiceConfig;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The Bot API calls the service makes on its own initiative.
+#[async_trait]
+pub trait TelegramApi: Send + Sync {
+    /// Returns the Stars of a successful payment to the user. A charge that
+    /// was already refunded counts as success.
+    async fn refund_star_payment(
+        &self,
+        user_id: i64,
+        telegram_payment_charge_id: &str,
+    ) -> Result<(), String>;
+}
+
+pub struct TelegramBotClient {
+    http: reqwest::Client,
+    api_url: String,
+    bot_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiResponse {
+    ok: bool,
+    description: Option<String>,
+}
+
+impl TelegramBotClient {
+    pub fn build_from_config(config: &ServiceConfig) -> Result<Self, String> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Error in building HTTP client: {}", e))?;
+        Ok(Self {
+            http,
+            api_url: config.telegram.api_url.clone(),
+            bot_token: config.bot_token.clone(),
+        })
+    }
+
+    async fn call(&self, method: &str, body: serde_json::Value) -> Result<ApiResponse, String> {
+        let url = format!("{}/bot{}/{}", self.api_url, self.bot_token, method);
+        let started = std::time::Instant::now();
+        let result = self.http.post(url).json(&body).send().await;
+        metrics::histogram!("telegram_api_duration_seconds", "method" => method.to_string())
+            .record(started.elapsed().as_secs_f64());
+        // The URL embeds the bot token, so only the error kind is reported.
+        let response = result
+            .map_err(|e| format!("Telegram {} request failed: {}", method, e.without_url()))?;
+        response.json::<ApiResponse>().await.map_err(|e| {
+            format!(
+                "Telegram {} response is malformed: {}",
+                method,
+                e.without_url()
+            )
+        })
+    }
+}
+
+#[async_trait]
+impl TelegramApi for TelegramBotClient {
+    async fn refund_star_payment(
+        &self,
+        user_id: i64,
+        telegram_payment_charge_id: &str,
+    ) -> Result<(), String> {
+        let response = self
+            .call(
+                "refundStarPayment",
+                json!({
+                    "user_id": user_id,
+                    "telegram_payment_charge_id": telegram_payment_charge_id,
+                }),
+            )
+            .await?;
+        let description = response.description.unwrap_or_default();
+        if response.ok || description.contains("CHARGE_ALREADY_REFUNDED") {
+            info!("Refunded Telegram payment {}", telegram_payment_charge_id);
+            return Ok(());
+        }
+        Err(format!(
+            "Telegram refused to refund payment {}: {}",
+            telegram_payment_charge_id, description
+        ))
+    }
+}
+
+/// Answers refunds with a fixed result instead of calling Telegram, and
+/// remembers which charges it was asked to refund.
+#[cfg(test)]
+pub struct StubTelegramApi {
+    pub result: Result<(), String>,
+    pub refunds: std::sync::Mutex<Vec<(i64, String)>>,
+}
+
+#[cfg(test)]
+impl StubTelegramApi {
+    pub fn new(result: Result<(), String>) -> Self {
+        Self {
+            result,
+            refunds: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+#[async_trait]
+impl TelegramApi for StubTelegramApi {
+    async fn refund_star_payment(
+        &self,
+        user_id: i64,
+        telegram_payment_charge_id: &str,
+    ) -> Result<(), String> {
+        self.refunds
+            .lock()
+            .unwrap()
+            .push((user_id, telegram_payment_charge_id.to_string()));
+        self.result.clone()
+    }
+}
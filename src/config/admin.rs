@@ -0,0 +1,84 @@
+use std::env;
+
+/// One support agent's credential for the admin routes.
+#[derive(Clone, Debug)]
+pub struct AdminKey {
+    /// Recorded as the actor in the audit log.
+    pub id: String,
+    pub key: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AdminConfig {
+    /// Expected in `X-Admin-Key` on admin routes; the matching key decides
+    /// who the request is audited as. Admin routes are disabled while empty.
+    pub api_keys: Vec<AdminKey>,
+}
+
+impl AdminConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.api_keys = parse_api_keys(&env::var("ADMIN_API_KEYS").unwrap_or_default())?;
+
+        Ok(())
+    }
+}
+
+/// Parses `id:key` pairs separated by commas. Ids and keys must be unique so
+/// every key names exactly one admin.
+fn parse_api_keys(value: &str) -> Result<Vec<AdminKey>, String> {
+    let mut api_keys: Vec<AdminKey> = Vec::new();
+    for entry in value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+    {
+        let (id, key) = entry
+            .split_once(':')
+            .map(|(id, key)| (id.trim(), key.trim()))
+            .filter(|(id, key)| !id.is_empty() && !key.is_empty())
+            .ok_or_else(|| "ADMIN_API_KEYS entries must look like 'id:key'".to_string())?;
+        if api_keys.iter().any(|existing| existing.id == id) {
+            return Err(format!("ADMIN_API_KEYS lists admin '{}' twice", id));
+        }
+        if api_keys.iter().any(|existing| existing.key == key) {
+            return Err(format!(
+                "ADMIN_API_KEYS reuses the key of another admin for '{}'",
+                id
+            ));
+        }
+        api_keys.push(AdminKey {
+            id: id.to_string(),
+            key: key.to_string(),
+        });
+    }
+    Ok(api_keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_api_keys_reads_id_key_pairs() {
+        let api_keys = parse_api_keys(" alice:k1 , bob:k2:with-colon ,").unwrap();
+        assert_eq!(api_keys.len(), 2);
+        assert_eq!(
+            (api_keys[0].id.as_str(), api_keys[0].key.as_str()),
+            ("alice", "k1")
+        );
+        assert_eq!(
+            (api_keys[1].id.as_str(), api_keys[1].key.as_str()),
+            ("bob", "k2:with-colon")
+        );
+        assert!(parse_api_keys("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_api_keys_rejects_malformed_and_duplicate_entries() {
+        assert!(parse_api_keys("just-a-key").is_err());
+        assert!(parse_api_keys(":k1").is_err());
+        assert!(parse_api_keys("alice:").is_err());
+        assert!(parse_api_keys("alice:k1,alice:k2").is_err());
+        assert!(parse_api_keys("alice:k1,bob:k1").is_err());
+    }
+}
@@ -0,0 +1,81 @@
+use std::env;
+use std::str::FromStr;
+
+use chrono::{DateTime, TimeDelta, Utc};
+
+use crate::entity::credit_bucket::CreditSource;
+
+/// What happens to a balance that no longer covers the credits a refunded
+/// payment granted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RefundPolicy {
+    /// Take back what is left and stop at zero.
+    #[default]
+    Clamp,
+    /// Take back everything, leaving the balance negative until it is repaid.
+    Debt,
+}
+
+impl FromStr for RefundPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "clamp" => Ok(Self::Clamp),
+            "debt" => Ok(Self::Debt),
+            other => Err(format!("Unknown refund policy: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BillingConfig {
+    pub refund_policy: RefundPolicy,
+    /// How long credits from one-time purchases last; `None` never expires.
+    pub purchase_credit_ttl: Option<TimeDelta>,
+    /// How long signup, promo, referral and refill credits last.
+    pub bonus_credit_ttl: Option<TimeDelta>,
+}
+
+impl BillingConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.refund_policy = match env::var("REFUND_BALANCE_POLICY") {
+            Ok(value) => value.parse::<RefundPolicy>().map_err(|_| {
+                "REFUND_BALANCE_POLICY must be either 'clamp' or 'debt'".to_string()
+            })?,
+            Err(_) => RefundPolicy::default(),
+        };
+
+        self.purchase_credit_ttl = ttl_from_env("CREDIT_EXPIRY_PURCHASE_DAYS")?;
+        self.bonus_credit_ttl = ttl_from_env("CREDIT_EXPIRY_BONUS_DAYS")?;
+
+        Ok(())
+    }
+
+    /// When credits granted from `source` at `now` expire. Subscription
+    /// credits last until the end of the period they were granted for, which
+    /// the caller knows, so they get `None` here.
+    pub fn credit_expiry(&self, source: CreditSource, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let ttl = match source {
+            CreditSource::Purchase => self.purchase_credit_ttl,
+            CreditSource::Signup
+            | CreditSource::Promo
+            | CreditSource::Referral
+            | CreditSource::Refill => self.bonus_credit_ttl,
+            CreditSource::Subscription | CreditSource::Adjustment => None,
+        };
+        ttl.map(|ttl| now + ttl)
+    }
+}
+
+fn ttl_from_env(name: &str) -> Result<Option<TimeDelta>, String> {
+    match env::var(name) {
+        Ok(value) if !value.is_empty() => value
+            .parse::<i64>()
+            .ok()
+            .filter(|days| *days > 0)
+            .map(|days| Some(TimeDelta::days(days)))
+            .ok_or_else(|| format!("{} must be a positive number of days", name)),
+        _ => Ok(None),
+    }
+}
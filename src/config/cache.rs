@@ -0,0 +1,68 @@
+use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheBackendKind {
+    #[default]
+    Redis,
+    Memory,
+}
+
+impl FromStr for CacheBackendKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "redis" => Ok(Self::Redis),
+            "memory" => Ok(Self::Memory),
+            other => Err(format!("Unknown cache backend: {other}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CacheConfig {
+    pub backend: CacheBackendKind,
+    pub session_ttl_jitter: f64,
+    pub session_early_refresh_window: Duration,
+    pub session_local_ttl: Duration,
+    pub invalidation_channel: String,
+}
+
+impl CacheConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.backend = match env::var("CACHE_BACKEND") {
+            Ok(value) => value
+                .parse::<CacheBackendKind>()
+                .map_err(|_| "CACHE_BACKEND must be either 'redis' or 'memory'".to_string())?,
+            Err(_) => CacheBackendKind::default(),
+        };
+
+        self.session_ttl_jitter = env::var("SESSION_CACHE_TTL_JITTER")
+            .unwrap_or_else(|_| "0.1".to_string())
+            .parse::<f64>()
+            .ok()
+            .filter(|jitter| (0.0..1.0).contains(jitter))
+            .ok_or_else(|| "SESSION_CACHE_TTL_JITTER must be in the range [0, 1)".to_string())?;
+
+        self.session_early_refresh_window = Duration::from_secs(
+            env::var("SESSION_CACHE_EARLY_REFRESH_SECS")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<u64>()
+                .map_err(|_| "SESSION_CACHE_EARLY_REFRESH_SECS is not a valid u64".to_string())?,
+        );
+
+        self.session_local_ttl = Duration::from_secs(
+            env::var("SESSION_LOCAL_CACHE_TTL_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse::<u64>()
+                .map_err(|_| "SESSION_LOCAL_CACHE_TTL_SECS is not a valid u64".to_string())?,
+        );
+
+        self.invalidation_channel = env::var("CACHE_INVALIDATION_CHANNEL")
+            .unwrap_or_else(|_| "user-service:invalidate".to_string());
+
+        Ok(())
+    }
+}
@@ -1,4 +1,6 @@
 use std::env;
+use std::time::Duration;
+
 #[derive(Debug, Clone, Default)]
 pub struct DatabaseConfig {
     pub username: String,
@@ -6,17 +8,34 @@ pub struct DatabaseConfig {
     pub port: u16,
     pub host: String,
     pub database: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub connect_timeout: Duration,
+    pub acquire_timeout: Duration,
+    pub idle_timeout: Duration,
+    pub max_lifetime: Duration,
+    pub ssl_mode: Option<String>,
+    pub ssl_root_cert: Option<String>,
+    pub statement_timeout: Option<Duration>,
+    pub application_name: String,
+    pub read_replica_url: Option<String>,
 }
 
 impl DatabaseConfig {
     pub fn get_url(&self) -> String {
-        Self::create_url(
+        self.with_connection_params(Self::create_url(
             &self.username,
             &self.password,
             &self.host,
             self.port,
             &self.database,
-        )
+        ))
+    }
+
+    pub fn get_read_replica_url(&self) -> Option<String> {
+        self.read_replica_url
+            .clone()
+            .map(|url| self.with_connection_params(url))
     }
 
     pub fn create_url(
@@ -29,6 +48,27 @@ impl DatabaseConfig {
         format!("postgres://{username}:{password}@{host}:{port}/{database_name}")
     }
 
+    /// Appends the SSL, statement timeout and application name settings to a
+    /// connection URL, keeping any query parameters it already has.
+    fn with_connection_params(&self, url: String) -> String {
+        let mut params = url::form_urlencoded::Serializer::new(String::new());
+        params.append_pair("application_name", &self.application_name);
+        if let Some(ssl_mode) = &self.ssl_mode {
+            params.append_pair("sslmode", ssl_mode);
+        }
+        if let Some(ssl_root_cert) = &self.ssl_root_cert {
+            params.append_pair("sslrootcert", ssl_root_cert);
+        }
+        if let Some(statement_timeout) = self.statement_timeout {
+            params.append_pair(
+                "options[statement_timeout]",
+                &statement_timeout.as_millis().to_string(),
+            );
+        }
+        let separator = if url.contains('?') { '&' } else { '?' };
+        format!("{url}{separator}{}", params.finish())
+    }
+
     pub fn init_from_env(&mut self) -> Result<(), String> {
         self.username = env::var("DB_USERNAME")
             .map_err(|_| "DB_USERNAME not set in environment".to_string())?;
@@ -47,6 +87,69 @@ impl DatabaseConfig {
         self.database = env::var("DB_DATABASE")
             .map_err(|_| "DB_DATABASE not set in environment".to_string())?;
 
+        self.max_connections = env::var("DB_MAX_CONNECTIONS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<u32>()
+            .map_err(|_| "DB_MAX_CONNECTIONS is not a valid u32".to_string())?;
+
+        self.min_connections = env::var("DB_MIN_CONNECTIONS")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()
+            .map_err(|_| "DB_MIN_CONNECTIONS is not a valid u32".to_string())?;
+
+        if self.min_connections > self.max_connections {
+            return Err("DB_MIN_CONNECTIONS must not exceed DB_MAX_CONNECTIONS".to_string());
+        }
+
+        self.connect_timeout = Duration::from_secs(
+            env::var("DB_CONNECT_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse::<u64>()
+                .map_err(|_| "DB_CONNECT_TIMEOUT_SECS is not a valid u64".to_string())?,
+        );
+
+        self.acquire_timeout = Duration::from_secs(
+            env::var("DB_ACQUIRE_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse::<u64>()
+                .map_err(|_| "DB_ACQUIRE_TIMEOUT_SECS is not a valid u64".to_string())?,
+        );
+
+        self.idle_timeout = Duration::from_secs(
+            env::var("DB_IDLE_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "600".to_string())
+                .parse::<u64>()
+                .map_err(|_| "DB_IDLE_TIMEOUT_SECS is not a valid u64".to_string())?,
+        );
+
+        self.max_lifetime = Duration::from_secs(
+            env::var("DB_MAX_LIFETIME_SECS")
+                .unwrap_or_else(|_| "1800".to_string())
+                .parse::<u64>()
+                .map_err(|_| "DB_MAX_LIFETIME_SECS is not a valid u64".to_string())?,
+        );
+
+        self.ssl_mode = env::var("DB_SSL_MODE").ok().filter(|v| !v.is_empty());
+
+        self.ssl_root_cert = env::var("DB_SSL_ROOT_CERT").ok().filter(|v| !v.is_empty());
+
+        self.statement_timeout = env::var("DB_STATEMENT_TIMEOUT_MS")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| {
+                v.parse::<u64>()
+                    .map(Duration::from_millis)
+                    .map_err(|_| "DB_STATEMENT_TIMEOUT_MS is not a valid u64".to_string())
+            })
+            .transpose()?;
+
+        self.application_name =
+            env::var("DB_APPLICATION_NAME").unwrap_or_else(|_| "user-service".to_string());
+
+        self.read_replica_url = env::var("DB_READ_REPLICA_URL")
+            .ok()
+            .filter(|v| !v.is_empty());
+
         Ok(())
     }
 }
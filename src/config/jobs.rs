@@ -0,0 +1,70 @@
+use std::env;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Default)]
+pub struct JobsConfig {
+    pub enabled: bool,
+    pub subscription_interval: Duration,
+    pub subscription_batch_size: u64,
+    pub refill_interval: Duration,
+    pub refill_batch_size: u64,
+    pub credit_expiry_interval: Duration,
+    pub credit_expiry_batch_size: u64,
+}
+
+impl JobsConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.enabled = env::var("JOBS_ENABLED")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .map_err(|_| "JOBS_ENABLED is not a valid bool".to_string())?;
+
+        self.subscription_interval = Duration::from_secs(
+            env::var("SUBSCRIPTION_JOB_INTERVAL_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or_else(|| {
+                    "SUBSCRIPTION_JOB_INTERVAL_SECS must be a positive integer".to_string()
+                })?,
+        );
+
+        self.subscription_batch_size = env::var("SUBSCRIPTION_JOB_BATCH_SIZE")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<u64>()
+            .map_err(|_| "SUBSCRIPTION_JOB_BATCH_SIZE is not a valid u64".to_string())?;
+
+        self.refill_interval = Duration::from_secs(
+            env::var("REFILL_JOB_INTERVAL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or_else(|| "REFILL_JOB_INTERVAL_SECS must be a positive integer".to_string())?,
+        );
+
+        self.refill_batch_size = env::var("REFILL_JOB_BATCH_SIZE")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse::<u64>()
+            .map_err(|_| "REFILL_JOB_BATCH_SIZE is not a valid u64".to_string())?;
+
+        self.credit_expiry_interval = Duration::from_secs(
+            env::var("CREDIT_EXPIRY_JOB_INTERVAL_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse::<u64>()
+                .ok()
+                .filter(|secs| *secs > 0)
+                .ok_or_else(|| {
+                    "CREDIT_EXPIRY_JOB_INTERVAL_SECS must be a positive integer".to_string()
+                })?,
+        );
+
+        self.credit_expiry_batch_size = env::var("CREDIT_EXPIRY_JOB_BATCH_SIZE")
+            .unwrap_or_else(|_| "500".to_string())
+            .parse::<u64>()
+            .map_err(|_| "CREDIT_EXPIRY_JOB_BATCH_SIZE is not a valid u64".to_string())?;
+
+        Ok(())
+    }
+}
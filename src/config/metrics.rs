@@ -0,0 +1,7 @@
+use metrics_exporter_prometheus::{PrometheusBuilder, PrometheusHandle};
+
+pub fn install_metrics_recorder() -> Result<PrometheusHandle, String> {
+    PrometheusBuilder::new()
+        .install_recorder()
+        .map_err(|e| format!("Failed to install metrics recorder: {}", e))
+}
@@ -1,8 +1,19 @@
+pub mod admin;
+pub mod billing;
+pub mod cache;
 pub mod db;
+pub mod jobs;
 pub mod jwt;
+pub mod metrics;
+pub mod quota;
+pub mod rate_limit;
 pub mod redis;
+pub mod referral;
+pub mod refill;
 pub mod secret;
 pub mod server;
+pub mod signup;
+pub mod telegram;
 pub mod tracing;
 use dotenv::dotenv;
 use std::env;
@@ -10,24 +21,48 @@ use std::env;
 #[derive(Clone, Default, Debug)]
 pub struct ServiceConfig {
     pub db: db::DatabaseConfig,
+    pub cache: cache::CacheConfig,
     pub redis: redis::RedisConfig,
     pub server: server::ServerConfig,
+    pub jobs: jobs::JobsConfig,
     pub secret: secret::SecretConfig,
     pub jwt: jwt::JWTConfig,
+    pub admin: admin::AdminConfig,
+    pub billing: billing::BillingConfig,
+    pub quota: quota::QuotaConfig,
+    pub rate_limit: rate_limit::RateLimitConfig,
+    pub referral: referral::ReferralConfig,
+    pub refill: refill::RefillConfig,
+    pub signup: signup::SignupConfig,
+    pub telegram: telegram::TelegramConfig,
     pub bot_token: String,
+    /// Credits granted by a `set_session` that flags a user as subscribed
+    /// without naming a plan, the request shape from before plans existed.
     pub charged_credit: i64,
 }
 impl ServiceConfig {
     pub fn init_from_env(&mut self) -> Result<(), String> {
         dotenv().ok();
         self.db.init_from_env()?;
-        self.redis.init_from_env()?;
+        self.cache.init_from_env()?;
+        if self.cache.backend == cache::CacheBackendKind::Redis {
+            self.redis.init_from_env()?;
+        }
         self.server.init_from_env()?;
+        self.jobs.init_from_env()?;
         self.jwt.init_from_env()?;
         self.secret.init_from_env()?;
-        self.charged_credit = 1000;
+        self.telegram.init_from_env()?;
+        self.admin.init_from_env()?;
+        self.billing.init_from_env()?;
+        self.quota.init_from_env()?;
+        self.rate_limit.init_from_env()?;
+        self.referral.init_from_env()?;
+        self.refill.init_from_env()?;
+        self.signup.init_from_env()?;
         self.bot_token =
             env::var("BOT_TOKEN").map_err(|_| "BOT_TOKEN not set in environment".to_string())?;
+        self.charged_credit = 1000;
         Ok(())
     }
 }
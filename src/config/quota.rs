@@ -0,0 +1,59 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Plan tier that users without an active subscription fall under.
+pub const FREE_TIER: &str = "free";
+
+/// One named quota: at most `limits[tier]` units per sliding `window`.
+#[derive(Debug, Clone)]
+pub struct QuotaDefinition {
+    pub window: Duration,
+    /// Keyed by plan tier; tiers that aren't listed are unlimited.
+    pub limits: HashMap<String, u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawQuota {
+    window_secs: u64,
+    limits: HashMap<String, u64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QuotaConfig {
+    /// Keyed by quota name, e.g. `requests`.
+    pub quotas: BTreeMap<String, QuotaDefinition>,
+}
+
+impl QuotaConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        let raw = match env::var("QUOTAS") {
+            Ok(value) if !value.is_empty() => {
+                serde_json::from_str::<BTreeMap<String, RawQuota>>(&value)
+                    .map_err(|e| format!("QUOTAS is not valid: {}", e))?
+            }
+            _ => BTreeMap::new(),
+        };
+
+        self.quotas = raw
+            .into_iter()
+            .map(|(name, quota)| {
+                if quota.window_secs == 0 {
+                    return Err(format!("Quota '{}' needs a positive window_secs", name));
+                }
+                Ok((
+                    name,
+                    QuotaDefinition {
+                        window: Duration::from_secs(quota.window_secs),
+                        limits: quota.limits,
+                    },
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+const DEFAULT_RATE_LIMITS: &str = r#"{"auth":{"burst":10,"per_minute":10},"session":{"burst":60,"per_minute":120},"promo":{"burst":5,"per_minute":5}}"#;
+
+/// Token bucket limits for one route group.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// Requests a client can make back to back.
+    pub burst: u64,
+    /// Sustained rate the bucket refills at.
+    pub per_minute: u64,
+}
+
+/// An address or CIDR range of reverse proxies whose forwarding header is
+/// believed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl TrustedProxy {
+    /// IPv4-mapped IPv6 addresses, as seen on dual-stack listeners, match
+    /// the IPv4 ranges they map to.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - self.prefix_len as u32)
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for TrustedProxy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+        let network = address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("Invalid proxy address: {s}"))?;
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_len)
+                .ok_or_else(|| format!("Invalid proxy prefix length: {s}"))?,
+            None => max_len,
+        };
+        // Store IPv4-mapped ranges as the IPv4 ranges they cover.
+        match network.to_canonical() {
+            IpAddr::V4(mapped) if network.is_ipv6() && prefix_len >= 96 => Ok(Self {
+                network: IpAddr::V4(mapped),
+                prefix_len: prefix_len - 96,
+            }),
+            _ => Ok(Self {
+                network,
+                prefix_len,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    /// Keyed by route group; groups without an entry are not limited.
+    pub groups: HashMap<String, RateLimit>,
+    pub trusted_proxies: Vec<TrustedProxy>,
+    /// Header trusted proxies put the client address chain in.
+    pub client_ip_header: String,
+}
+
+impl RateLimitConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        let rate_limits =
+            env::var("RATE_LIMITS").unwrap_or_else(|_| DEFAULT_RATE_LIMITS.to_string());
+        self.groups = if rate_limits.is_empty() {
+            HashMap::new()
+        } else {
+            serde_json::from_str::<HashMap<String, RateLimit>>(&rate_limits)
+                .map_err(|e| format!("RATE_LIMITS is not valid: {}", e))?
+        };
+        if let Some((group, _)) = self
+            .groups
+            .iter()
+            .find(|(_, limit)| limit.burst == 0 || limit.per_minute == 0)
+        {
+            return Err(format!(
+                "Rate limit '{}' needs a positive burst and per_minute",
+                group
+            ));
+        }
+
+        self.trusted_proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| {
+                proxy
+                    .parse::<TrustedProxy>()
+                    .map_err(|e| format!("TRUSTED_PROXIES is not valid: {}", e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        self.client_ip_header =
+            env::var("CLIENT_IP_HEADER").unwrap_or_else(|_| "X-Forwarded-For".to_string());
+
+        Ok(())
+    }
+
+    pub fn is_trusted_proxy(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|proxy| proxy.contains(ip))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy(s: &str) -> TrustedProxy {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn contains_matches_cidr_ranges() {
+        let range = proxy("10.1.0.0/16");
+        assert!(range.contains(ip("10.1.0.1")));
+        assert!(range.contains(ip("10.1.255.255")));
+        assert!(!range.contains(ip("10.2.0.1")));
+        assert!(!range.contains(ip("fd00::1")));
+
+        assert!(proxy("0.0.0.0/0").contains(ip("192.0.2.1")));
+        assert!(proxy("192.0.2.7").contains(ip("192.0.2.7")));
+        assert!(!proxy("192.0.2.7").contains(ip("192.0.2.8")));
+
+        let range = proxy("fd00:1::/32");
+        assert!(range.contains(ip("fd00:1:ffff::1")));
+        assert!(!range.contains(ip("fd00:2::1")));
+    }
+
+    #[test]
+    fn contains_matches_ipv4_mapped_addresses() {
+        let range = proxy("10.1.0.0/16");
+        assert!(range.contains(ip("::ffff:10.1.2.3")));
+        assert!(!range.contains(ip("::ffff:10.2.2.3")));
+
+        let mapped = proxy("::ffff:10.1.0.0/112");
+        assert_eq!(mapped, range);
+        assert!(mapped.contains(ip("10.1.2.3")));
+    }
+
+    #[test]
+    fn from_str_rejects_bad_prefixes() {
+        assert!("10.0.0.0/33".parse::<TrustedProxy>().is_err());
+        assert!("fd00::/129".parse::<TrustedProxy>().is_err());
+        assert!("10.0.0.0/x".parse::<TrustedProxy>().is_err());
+        assert!("proxy.local".parse::<TrustedProxy>().is_err());
+    }
+}
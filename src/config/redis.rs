@@ -1,31 +1,259 @@
 use std::env;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RedisTopology {
+    #[default]
+    Standalone,
+    Sentinel,
+    Cluster,
+}
+
+impl FromStr for RedisTopology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "standalone" => Ok(Self::Standalone),
+            "sentinel" => Ok(Self::Sentinel),
+            "cluster" => Ok(Self::Cluster),
+            other => Err(format!("Unknown redis topology: {other}")),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct RedisConfig {
+    pub topology: RedisTopology,
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
     pub port: u16,
     pub host: String,
-    pub database: String,
+    pub database: i64,
+    pub tls: bool,
+    pub ca_file: Option<String>,
+    pub sentinel_master: String,
+    pub sentinel_nodes: Vec<String>,
+    pub sentinel_password: Option<String>,
+    pub cluster_nodes: Vec<String>,
+    pub connect_timeout: Duration,
+    pub response_timeout: Duration,
+    pub reconnect_retries: usize,
+    pub reconnect_factor_ms: u64,
+    pub reconnect_exponent_base: u64,
+    pub reconnect_max_delay_ms: u64,
 }
 
 impl RedisConfig {
     pub fn get_url(&self) -> String {
-        Self::create_url(&self.host, self.port, &self.database)
+        match &self.url {
+            Some(url) => url.clone(),
+            None => Self::create_url(
+                self.tls,
+                self.username.as_deref(),
+                self.password.as_deref(),
+                &self.host,
+                self.port,
+                self.database,
+            ),
+        }
     }
 
-    pub fn create_url(host: &str, port: u16, database_name: &str) -> String {
-        format!("redis://{host}:{port}/{database_name}")
+    pub fn create_url(
+        tls: bool,
+        username: Option<&str>,
+        password: Option<&str>,
+        host: &str,
+        port: u16,
+        database: i64,
+    ) -> String {
+        let scheme = if tls { "rediss" } else { "redis" };
+        let credentials = match (username, password) {
+            (Some(username), Some(password)) => format!(
+                "{}:{}@",
+                Self::encode_userinfo(username),
+                Self::encode_userinfo(password)
+            ),
+            (None, Some(password)) => format!(":{}@", Self::encode_userinfo(password)),
+            (Some(username), None) => format!("{}@", Self::encode_userinfo(username)),
+            (None, None) => String::new(),
+        };
+        format!("{scheme}://{credentials}{host}:{port}/{database}")
     }
 
-    pub fn init_from_env(&mut self) -> Result<(), String> {
-        self.host =
-            env::var("REDIS_HOST").map_err(|_| "REDIS_HOST not set in environment".to_string())?;
+    /// Builds a URL for one `host:port` node of a sentinel or cluster deployment,
+    /// carrying the configured credentials and TLS mode.
+    pub fn get_node_url(&self, node: &str, database: i64) -> Result<String, String> {
+        let (host, port) = Self::split_node(node)?;
+        Ok(Self::create_url(
+            self.tls,
+            self.username.as_deref(),
+            self.password.as_deref(),
+            host,
+            port,
+            database,
+        ))
+    }
 
-        self.port = env::var("REDIS_PORT")
-            .map_err(|_| "REDIS_PORT not set in environment".to_string())?
+    pub fn get_sentinel_urls(&self) -> Result<Vec<String>, String> {
+        self.sentinel_nodes
+            .iter()
+            .map(|node| {
+                let (host, port) = Self::split_node(node)?;
+                Ok(Self::create_url(
+                    self.tls,
+                    None,
+                    self.sentinel_password.as_deref(),
+                    host,
+                    port,
+                    0,
+                ))
+            })
+            .collect()
+    }
+
+    pub fn get_cluster_urls(&self) -> Result<Vec<String>, String> {
+        self.cluster_nodes
+            .iter()
+            .map(|node| self.get_node_url(node, 0))
+            .collect()
+    }
+
+    fn split_node(node: &str) -> Result<(&str, u16), String> {
+        let (host, port) = node
+            .rsplit_once(':')
+            .ok_or_else(|| format!("Redis node '{node}' is not in host:port form"))?;
+        let port = port
             .parse::<u16>()
-            .map_err(|_| "REDIS_PORT is not a valid u16".to_string())?;
+            .map_err(|_| format!("Redis node '{node}' has an invalid port"))?;
+        Ok((host, port))
+    }
+
+    fn parse_nodes(value: &str) -> Vec<String> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|node| !node.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn encode_userinfo(value: &str) -> String {
+        url::form_urlencoded::byte_serialize(value.as_bytes())
+            .collect::<String>()
+            .replace('+', "%20")
+    }
+
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.topology = match env::var("REDIS_TOPOLOGY") {
+            Ok(value) => value.parse::<RedisTopology>().map_err(|_| {
+                "REDIS_TOPOLOGY must be one of 'standalone', 'sentinel' or 'cluster'".to_string()
+            })?,
+            Err(_) => RedisTopology::default(),
+        };
+        self.url = env::var("REDIS_URL").ok().filter(|v| !v.is_empty());
+        self.ca_file = env::var("REDIS_CA_FILE").ok().filter(|v| !v.is_empty());
+
+        if self.url.is_some() && self.topology != RedisTopology::Standalone {
+            return Err("REDIS_URL is only supported with the standalone topology".to_string());
+        }
+
+        if let Some(url) = &self.url {
+            self.tls = url.starts_with("rediss://");
+        } else {
+            if self.topology == RedisTopology::Standalone {
+                self.host = env::var("REDIS_HOST")
+                    .map_err(|_| "REDIS_HOST not set in environment".to_string())?;
+
+                self.port = env::var("REDIS_PORT")
+                    .map_err(|_| "REDIS_PORT not set in environment".to_string())?
+                    .parse::<u16>()
+                    .map_err(|_| "REDIS_PORT is not a valid u16".to_string())?;
+            }
+
+            self.database = env::var("REDIS_DATABASE")
+                .unwrap_or_else(|_| "0".to_string())
+                .parse::<i64>()
+                .map_err(|_| "REDIS_DATABASE is not a valid database index".to_string())?;
+
+            self.username = env::var("REDIS_USERNAME").ok().filter(|v| !v.is_empty());
+            self.password = env::var("REDIS_PASSWORD").ok().filter(|v| !v.is_empty());
+
+            self.tls = env::var("REDIS_TLS")
+                .unwrap_or_else(|_| "false".to_string())
+                .parse::<bool>()
+                .map_err(|_| "REDIS_TLS is not a valid bool".to_string())?;
+        }
+
+        if self.ca_file.is_some() && !self.tls {
+            return Err("REDIS_CA_FILE requires a TLS (rediss://) connection".to_string());
+        }
+
+        match self.topology {
+            RedisTopology::Standalone => {}
+            RedisTopology::Sentinel => {
+                self.sentinel_master = env::var("REDIS_SENTINEL_MASTER")
+                    .map_err(|_| "REDIS_SENTINEL_MASTER not set in environment".to_string())?;
+                self.sentinel_nodes = Self::parse_nodes(
+                    &env::var("REDIS_SENTINEL_NODES")
+                        .map_err(|_| "REDIS_SENTINEL_NODES not set in environment".to_string())?,
+                );
+                if self.sentinel_nodes.is_empty() {
+                    return Err("REDIS_SENTINEL_NODES must list at least one node".to_string());
+                }
+                self.sentinel_password = env::var("REDIS_SENTINEL_PASSWORD")
+                    .ok()
+                    .filter(|v| !v.is_empty());
+            }
+            RedisTopology::Cluster => {
+                self.cluster_nodes = Self::parse_nodes(
+                    &env::var("REDIS_CLUSTER_NODES")
+                        .map_err(|_| "REDIS_CLUSTER_NODES not set in environment".to_string())?,
+                );
+                if self.cluster_nodes.is_empty() {
+                    return Err("REDIS_CLUSTER_NODES must list at least one node".to_string());
+                }
+                if self.database != 0 {
+                    return Err("REDIS_DATABASE must be 0 with the cluster topology".to_string());
+                }
+            }
+        }
+
+        self.connect_timeout = Duration::from_millis(
+            env::var("REDIS_CONNECT_TIMEOUT_MS")
+                .unwrap_or_else(|_| "2000".to_string())
+                .parse::<u64>()
+                .map_err(|_| "REDIS_CONNECT_TIMEOUT_MS is not a valid u64".to_string())?,
+        );
+
+        self.response_timeout = Duration::from_millis(
+            env::var("REDIS_RESPONSE_TIMEOUT_MS")
+                .unwrap_or_else(|_| "1000".to_string())
+                .parse::<u64>()
+                .map_err(|_| "REDIS_RESPONSE_TIMEOUT_MS is not a valid u64".to_string())?,
+        );
+
+        self.reconnect_retries = env::var("REDIS_RECONNECT_RETRIES")
+            .unwrap_or_else(|_| "6".to_string())
+            .parse::<usize>()
+            .map_err(|_| "REDIS_RECONNECT_RETRIES is not a valid usize".to_string())?;
+
+        self.reconnect_factor_ms = env::var("REDIS_RECONNECT_FACTOR_MS")
+            .unwrap_or_else(|_| "100".to_string())
+            .parse::<u64>()
+            .map_err(|_| "REDIS_RECONNECT_FACTOR_MS is not a valid u64".to_string())?;
+
+        self.reconnect_exponent_base = env::var("REDIS_RECONNECT_EXPONENT_BASE")
+            .unwrap_or_else(|_| "2".to_string())
+            .parse::<u64>()
+            .map_err(|_| "REDIS_RECONNECT_EXPONENT_BASE is not a valid u64".to_string())?;
 
-        self.database = env::var("REDIS_DATABASE")
-            .map_err(|_| "REDIS_DATABASE not set in environment".to_string())?;
+        self.reconnect_max_delay_ms = env::var("REDIS_RECONNECT_MAX_DELAY_MS")
+            .unwrap_or_else(|_| "5000".to_string())
+            .parse::<u64>()
+            .map_err(|_| "REDIS_RECONNECT_MAX_DELAY_MS is not a valid u64".to_string())?;
 
         Ok(())
     }
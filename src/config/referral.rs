@@ -0,0 +1,39 @@
+use std::env;
+
+#[derive(Debug, Clone, Default)]
+pub struct ReferralConfig {
+    pub referrer_bonus: i64,
+    pub invitee_bonus: i64,
+    /// Hold both bonuses until the invitee's first payment.
+    pub require_purchase: bool,
+    /// Mini app link the referral code is appended to, e.g.
+    /// `https://t.me/my_bot/app?startapp=`.
+    pub link_base: String,
+}
+
+impl ReferralConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.referrer_bonus = env::var("REFERRAL_REFERRER_BONUS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|bonus| *bonus >= 0)
+            .ok_or_else(|| "REFERRAL_REFERRER_BONUS must be a non-negative integer".to_string())?;
+
+        self.invitee_bonus = env::var("REFERRAL_INVITEE_BONUS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|bonus| *bonus >= 0)
+            .ok_or_else(|| "REFERRAL_INVITEE_BONUS must be a non-negative integer".to_string())?;
+
+        self.require_purchase = env::var("REFERRAL_REQUIRE_PURCHASE")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| "REFERRAL_REQUIRE_PURCHASE is not a valid bool".to_string())?;
+
+        self.link_base = env::var("REFERRAL_LINK_BASE").unwrap_or_default();
+
+        Ok(())
+    }
+}
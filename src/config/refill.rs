@@ -0,0 +1,56 @@
+use std::env;
+
+use chrono::TimeDelta;
+
+/// Free credits topped up periodically. Disabled while `amount` is zero.
+#[derive(Debug, Clone, Default)]
+pub struct RefillConfig {
+    /// Credits granted per refill.
+    pub amount: i64,
+    /// Refills never raise a balance above this.
+    pub cap: i64,
+    pub period: TimeDelta,
+    /// Skip users with an active subscription.
+    pub non_subscribers_only: bool,
+}
+
+impl RefillConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.amount = env::var("CREDIT_REFILL_AMOUNT")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|amount| *amount >= 0)
+            .ok_or_else(|| "CREDIT_REFILL_AMOUNT must be a non-negative integer".to_string())?;
+
+        self.cap = match env::var("CREDIT_REFILL_CAP") {
+            Ok(value) if !value.is_empty() => value
+                .parse::<i64>()
+                .ok()
+                .filter(|cap| *cap >= 0)
+                .ok_or_else(|| "CREDIT_REFILL_CAP must be a non-negative integer".to_string())?,
+            _ => self.amount,
+        };
+
+        self.period = match env::var("CREDIT_REFILL_PERIOD")
+            .unwrap_or_else(|_| "daily".to_string())
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "daily" => TimeDelta::days(1),
+            "weekly" => TimeDelta::weeks(1),
+            _ => return Err("CREDIT_REFILL_PERIOD must be either 'daily' or 'weekly'".to_string()),
+        };
+
+        self.non_subscribers_only = env::var("CREDIT_REFILL_NON_SUBSCRIBERS_ONLY")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .map_err(|_| "CREDIT_REFILL_NON_SUBSCRIBERS_ONLY is not a valid bool".to_string())?;
+
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.amount > 0 && self.cap > 0
+    }
+}
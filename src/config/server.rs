@@ -5,6 +5,10 @@ use std::net::{AddrParseError, SocketAddr};
 pub struct ServerConfig {
     pub addr: String,
     pub port: u16,
+    /// `/metrics` is served on its own listener so it can stay off the
+    /// public network.
+    pub metrics_addr: String,
+    pub metrics_port: u16,
 }
 
 impl ServerConfig {
@@ -20,6 +24,10 @@ impl ServerConfig {
         self.get_addr().parse()
     }
 
+    pub fn get_metrics_socket_addr(&self) -> Result<SocketAddr, AddrParseError> {
+        format!("{}:{}", self.metrics_addr, self.metrics_port).parse()
+    }
+
     pub fn init_from_env(&mut self) -> Result<(), String> {
         self.addr = env::var("SERVER_ADDR")
             .map_err(|_| "SERVER_ADDR not set in environment".to_string())?;
@@ -29,6 +37,18 @@ impl ServerConfig {
             .parse::<u16>()
             .map_err(|_| "SERVER_PORT is not a valid u16".to_string())?;
 
+        self.metrics_addr = match env::var("METRICS_ADDR") {
+            Ok(value) if !value.is_empty() => value,
+            _ => "127.0.0.1".to_string(),
+        };
+
+        self.metrics_port = match env::var("METRICS_PORT") {
+            Ok(value) if !value.is_empty() => value,
+            _ => "9100".to_string(),
+        }
+        .parse::<u16>()
+        .map_err(|_| "METRICS_PORT is not a valid u16".to_string())?;
+
         Ok(())
     }
 }
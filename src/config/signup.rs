@@ -0,0 +1,152 @@
+use std::collections::HashMap;
+use std::env;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{
+    dto::session_data::{Preferences, SchemaDocument, SessionMetadata},
+    utils::merge_patch,
+};
+
+/// What a brand-new account starts with.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SignupDefaults {
+    pub credits: i64,
+    pub preferences: Value,
+    pub session_metadata: Value,
+}
+
+/// Changes to the defaults for one tenant or campaign. Documents are merge
+/// patches applied on top of whatever they override.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SignupOverride {
+    pub credits: Option<i64>,
+    pub preferences: Option<Value>,
+    pub session_metadata: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignupOverrides {
+    #[serde(default)]
+    tenants: HashMap<String, SignupOverride>,
+    /// Keyed by the `start_param` the mini app was opened with.
+    #[serde(default)]
+    campaigns: HashMap<String, SignupOverride>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignupConfig {
+    defaults: SignupDefaults,
+    /// Each campaign's override already applied to `defaults`.
+    campaigns: HashMap<String, SignupDefaults>,
+}
+
+impl Default for SignupConfig {
+    fn default() -> Self {
+        Self {
+            defaults: SignupDefaults {
+                credits: 0,
+                preferences: json!({}),
+                session_metadata: json!({}),
+            },
+            campaigns: HashMap::new(),
+        }
+    }
+}
+
+impl SignupConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        let credits = env::var("SIGNUP_CREDITS")
+            .unwrap_or_else(|_| "15".to_string())
+            .parse::<i64>()
+            .ok()
+            .filter(|credits| *credits >= 0)
+            .ok_or_else(|| "SIGNUP_CREDITS must be a non-negative integer".to_string())?;
+
+        let preferences = match env::var("SIGNUP_PREFERENCES") {
+            Ok(value) => serde_json::from_str::<Value>(&value)
+                .map_err(|_| "SIGNUP_PREFERENCES is not valid JSON".to_string())?,
+            Err(_) => json!({ "default_mode": "GPT-4o", "notifications": true }),
+        };
+
+        let session_metadata = match env::var("SIGNUP_SESSION_METADATA") {
+            Ok(value) => serde_json::from_str::<Value>(&value)
+                .map_err(|_| "SIGNUP_SESSION_METADATA is not valid JSON".to_string())?,
+            Err(_) => json!({ "last_mode_used": "GPT-4o", "recent_actions": ["request_made"] }),
+        };
+
+        let overrides = match env::var("SIGNUP_OVERRIDES") {
+            Ok(value) if !value.is_empty() => serde_json::from_str::<SignupOverrides>(&value)
+                .map_err(|e| format!("SIGNUP_OVERRIDES is not valid: {}", e))?,
+            _ => SignupOverrides::default(),
+        };
+
+        let mut defaults = SignupDefaults {
+            credits,
+            preferences,
+            session_metadata,
+        };
+        // The tenant is fixed per deployment, so its override is folded into
+        // the defaults once here rather than on every signup.
+        if let Ok(tenant) = env::var("SIGNUP_TENANT") {
+            if let Some(tenant_override) = overrides.tenants.get(&tenant) {
+                defaults = apply(defaults, tenant_override);
+            } else if !tenant.is_empty() {
+                return Err(format!(
+                    "SIGNUP_TENANT '{tenant}' has no entry in SIGNUP_OVERRIDES"
+                ));
+            }
+        }
+
+        self.defaults =
+            validate(defaults).map_err(|e| format!("Signup defaults are invalid: {}", e))?;
+        self.campaigns = overrides
+            .campaigns
+            .iter()
+            .map(|(campaign, campaign_override)| {
+                validate(apply(self.defaults.clone(), campaign_override))
+                    .map(|defaults| (campaign.clone(), defaults))
+                    .map_err(|e| format!("Signup campaign '{}' is invalid: {}", campaign, e))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(())
+    }
+
+    /// The defaults for a signup that came in through `start_param`.
+    pub fn resolve(&self, start_param: Option<&str>) -> SignupDefaults {
+        start_param
+            .and_then(|param| self.campaigns.get(param))
+            .unwrap_or(&self.defaults)
+            .clone()
+    }
+}
+
+fn apply(mut defaults: SignupDefaults, signup_override: &SignupOverride) -> SignupDefaults {
+    if let Some(credits) = signup_override.credits {
+        defaults.credits = credits;
+    }
+    if let Some(patch) = &signup_override.preferences {
+        merge_patch::apply(&mut defaults.preferences, patch);
+    }
+    if let Some(patch) = &signup_override.session_metadata {
+        merge_patch::apply(&mut defaults.session_metadata, patch);
+    }
+    defaults
+}
+
+/// Normalizes the documents into their stored form, failing on anything the
+/// session schemas would reject.
+fn validate(defaults: SignupDefaults) -> Result<SignupDefaults, String> {
+    if defaults.credits < 0 {
+        return Err("credits cannot be negative".to_string());
+    }
+    Ok(SignupDefaults {
+        credits: defaults.credits,
+        preferences: Preferences::parse_for_write(defaults.preferences)?,
+        session_metadata: SessionMetadata::parse_for_write(defaults.session_metadata)?,
+    })
+}
@@ -0,0 +1,24 @@
+use std::env;
+
+#[derive(Clone, Debug, Default)]
+pub struct TelegramConfig {
+    /// Sent by Telegram in `X-Telegram-Bot-Api-Secret-Token` on every webhook
+    /// call. The webhook route rejects everything while this is unset.
+    pub webhook_secret: Option<String>,
+    pub api_url: String,
+}
+
+impl TelegramConfig {
+    pub fn init_from_env(&mut self) -> Result<(), String> {
+        self.webhook_secret = env::var("TELEGRAM_WEBHOOK_SECRET")
+            .ok()
+            .filter(|secret| !secret.is_empty());
+
+        self.api_url = env::var("TELEGRAM_API_URL")
+            .unwrap_or_else(|_| "https://api.telegram.org".to_string())
+            .trim_end_matches('/')
+            .to_string();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1271 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{ConnectionTrait, Set};
+use serde_json::json;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    dto::{
+        request::{
+            AdjustCreditsRequest, BanUserRequest, CreateModelPriceRequest, CreatePromoCodeRequest,
+            RefundPaymentRequest, SearchUsersQuery, UnbanUserRequest,
+        },
+        response::{
+            AdjustCreditsResponse, AdminUserResponse, AdminUserSummary, ModelPriceResponse,
+            PromoCodeResponse, RefundPaymentResponse, UserStatusResponse,
+        },
+    },
+    entity::{
+        credit_bucket::CreditSource,
+        credit_ledger::LedgerEntryKind,
+        model_price,
+        payment::PaymentStatus,
+        plan::BillingPeriod,
+        promo_code,
+        user::{self, UserStatus},
+    },
+    repositories,
+    utils::{
+        self,
+        admin::AdminActor,
+        session::{MissingSessionKey, SessionKey},
+        transaction::UnitOfWork,
+    },
+    ServiceState,
+};
+
+const USER_SEARCH_LIMIT: u64 = 20;
+const RECENT_LEDGER_ENTRIES: u64 = 50;
+
+/// Refunds a Stars payment and reverses what it paid for.
+///
+/// The payment is first marked `refund_pending` on its own, then Telegram is
+/// asked to refund it with no rows locked, and only then is the reversal
+/// written in a short transaction. A payment left pending by a failed Bot API
+/// call or a failed commit is finished by retrying the refund: Telegram
+/// reports the charge as already refunded and the reversal is applied then.
+pub async fn refund_payment(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(charge_id): Path<String>,
+    Json(req): Json<RefundPaymentRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'refund_payment' request from admin {} for payment {}",
+        actor, charge_id
+    );
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to refund payment {}: {}", charge_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+    let already_refunded = || {
+        let error_message = format!("Payment {} is already refunded", charge_id);
+        error!("{}", error_message);
+        (StatusCode::CONFLICT, error_message)
+    };
+
+    let payment = repositories::payment::find_by_telegram_charge_id(state.db.as_ref(), &charge_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let error_message = format!("Payment not found: {}", charge_id);
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
+        })?;
+
+    let policy = state.config.billing.refund_policy;
+    match payment.status {
+        PaymentStatus::Refunded => return Err(already_refunded()),
+        PaymentStatus::RefundPending => {
+            info!("Resuming pending refund of payment {}", charge_id);
+        }
+        PaymentStatus::Paid | PaymentStatus::Unmatched => {
+            let transaction = UnitOfWork::begin(&state.db)
+                .await
+                .map_err(|e| internal_error(e.to_string()))?;
+            if !repositories::payment::mark_refund_pending(&transaction, payment.id, Utc::now())
+                .await
+                .map_err(internal_error)?
+            {
+                return Err(already_refunded());
+            }
+            repositories::audit_log::record(
+                &transaction,
+                &actor,
+                "payment.refund_requested",
+                Some(payment.user_id),
+                json!({
+                    "telegram_payment_charge_id": charge_id,
+                    "reason": req.reason,
+                }),
+                Utc::now(),
+            )
+            .await
+            .map_err(internal_error)?;
+            transaction
+                .commit()
+                .await
+                .map_err(|e| internal_error(e.to_string()))?;
+        }
+    }
+
+    state
+        .telegram
+        .refund_star_payment(payment.user_id, &charge_id)
+        .await
+        .map_err(|e| {
+            let error_message = format!(
+                "Telegram refund of payment {} failed, it stays pending until retried: {}",
+                charge_id, e
+            );
+            error!("{}", error_message);
+            (StatusCode::BAD_GATEWAY, error_message)
+        })?;
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    let now = Utc::now();
+    let reversal = utils::billing::reverse_payment(&transaction, &payment, policy, now)
+        .await
+        .map_err(|e| internal_error(format!("reversal failed, retry the refund: {}", e)))?
+        .ok_or_else(already_refunded)?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "payment.refund",
+        Some(payment.user_id),
+        json!({
+            "telegram_payment_charge_id": charge_id,
+            "reason": req.reason,
+            "policy": format!("{:?}", policy),
+            "credits_granted": payment.credits_granted,
+            "credits_reversed": reversal.credits_reversed,
+            "subscription_canceled": reversal.subscription_canceled,
+        }),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    if let Some(session) = reversal.session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey {
+                    user_id: payment.user_id,
+                },
+                session,
+            ),
+        );
+    }
+
+    transaction.commit().await.map_err(|e| {
+        internal_error(format!(
+            "refunded by Telegram but recording it failed, retry the refund: {}",
+            e
+        ))
+    })?;
+
+    metrics::counter!("payments_refunded_total", "source" => "admin").increment(1);
+    info!(
+        "Payment {} refunded by admin {}: {} credits reversed",
+        charge_id, actor, reversal.credits_reversed
+    );
+
+    let response = Json(RefundPaymentResponse {
+        telegram_payment_charge_id: charge_id,
+        user_id: payment.user_id,
+        credits_reversed: reversal.credits_reversed,
+        subscription_canceled: reversal.subscription_canceled,
+    })
+    .into_response();
+    Ok(response)
+}
+
+pub async fn create_promo_code(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Json(req): Json<CreatePromoCodeRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'create_promo_code' request from admin {} for code {}",
+        actor, req.code
+    );
+
+    let code = req.code.trim().to_uppercase();
+    validate_promo_code(&code, &req).map_err(|error_message| {
+        error!("{}", error_message);
+        (StatusCode::UNPROCESSABLE_ENTITY, error_message)
+    })?;
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to create promo code {}: {}", code, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    if repositories::promo_code::find_by_code(&*transaction, &code)
+        .await
+        .map_err(internal_error)?
+        .is_some()
+    {
+        let error_message = format!("Promo code {} already exists", code);
+        error!("{}", error_message);
+        return Err((StatusCode::CONFLICT, error_message));
+    }
+
+    let plan_id = match &req.plan_code {
+        Some(plan_code) => {
+            let plan = repositories::plan::find_by_code(&*transaction, plan_code)
+                .await
+                .map_err(internal_error)?
+                .filter(|plan| plan.period != BillingPeriod::OneTime)
+                .ok_or_else(|| {
+                    let error_message = format!("No subscription plan with code '{}'", plan_code);
+                    error!("{}", error_message);
+                    (StatusCode::UNPROCESSABLE_ENTITY, error_message)
+                })?;
+            Some(plan.id)
+        }
+        None => None,
+    };
+
+    let now = Utc::now();
+    let promo = repositories::promo_code::save(
+        &transaction,
+        promo_code::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            code: Set(code.clone()),
+            credits: Set(req.credits),
+            plan_id: Set(plan_id),
+            trial_days: Set(req.trial_days),
+            max_redemptions: Set(req.max_redemptions),
+            per_user_limit: Set(req.per_user_limit.unwrap_or(1)),
+            redemption_count: Set(0),
+            valid_from: Set(req.valid_from),
+            valid_until: Set(req.valid_until),
+            is_active: Set(true),
+            created_by: Set(actor.clone()),
+            created_at: Set(now),
+            updated_at: Set(now),
+        },
+    )
+    .await
+    .map_err(internal_error)?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "promo_code.create",
+        None,
+        json!(PromoCodeResponse::from(promo.clone())),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    info!("Promo code {} created by admin {}", code, actor);
+
+    let response = (StatusCode::CREATED, Json(PromoCodeResponse::from(promo))).into_response();
+    Ok(response)
+}
+
+pub async fn deactivate_promo_code(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(code): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'deactivate_promo_code' request from admin {} for code {}",
+        actor, code
+    );
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to deactivate promo code {}: {}", code, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let promo = repositories::promo_code::find_by_code(&*transaction, &code)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let error_message = format!("Promo code not found: {}", code);
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
+        })?;
+
+    let now = Utc::now();
+    let promo = repositories::promo_code::deactivate(&transaction, promo.id, now)
+        .await
+        .map_err(internal_error)?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "promo_code.deactivate",
+        None,
+        json!({ "code": promo.code, "redemption_count": promo.redemption_count }),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    info!("Promo code {} deactivated by admin {}", promo.code, actor);
+
+    let response = Json(PromoCodeResponse::from(promo)).into_response();
+    Ok(response)
+}
+
+/// Adds a price to the catalogue. Prices are never edited in place: a new
+/// row with a later `effective_from` supersedes the old one, so past charges
+/// can still be traced to the price that produced them.
+pub async fn create_model_price(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Json(req): Json<CreateModelPriceRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'create_model_price' request from admin {} for model {}",
+        actor, req.model
+    );
+
+    let model = req.model.trim().to_string();
+    let per_units = req.per_units.unwrap_or(1);
+    if model.is_empty() || req.credits < 0 || per_units <= 0 {
+        let error_message =
+            "A price needs a 'model', non-negative 'credits' and positive 'per_units'".to_string();
+        error!("{}", error_message);
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, error_message));
+    }
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to create price for model {}: {}", model, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let now = Utc::now();
+    let price = repositories::model_price::save(
+        &transaction,
+        model_price::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            model: Set(model.clone()),
+            unit: Set(req.unit),
+            credits: Set(req.credits),
+            per_units: Set(per_units),
+            effective_from: Set(req.effective_from.unwrap_or(now)),
+            created_by: Set(actor.clone()),
+            created_at: Set(now),
+        },
+    )
+    .await
+    .map_err(internal_error)?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "model_price.create",
+        None,
+        json!(ModelPriceResponse::from(price.clone())),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    info!(
+        "Price for {:?} units of model {} set by admin {}",
+        price.unit, model, actor
+    );
+
+    let response = (StatusCode::CREATED, Json(ModelPriceResponse::from(price))).into_response();
+    Ok(response)
+}
+
+pub async fn list_model_prices(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!("Received 'list_model_prices' request from admin {}", actor);
+
+    let prices = repositories::model_price::find_all(state.db_read.as_ref())
+        .await
+        .map_err(|e| {
+            let error_message = format!("Failed to list model prices: {}", e);
+            error!("{}", error_message);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+        })?;
+
+    let response = Json(
+        prices
+            .into_iter()
+            .map(ModelPriceResponse::from)
+            .collect::<Vec<_>>(),
+    )
+    .into_response();
+    Ok(response)
+}
+
+fn validate_promo_code(code: &str, req: &CreatePromoCodeRequest) -> Result<(), String> {
+    if code.is_empty()
+        || code.len() > 32
+        || !code
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err("Promo codes must be 1-32 letters, digits, '-' or '_'".to_string());
+    }
+    if req.credits < 0 {
+        return Err("'credits' cannot be negative".to_string());
+    }
+    if req.credits == 0 && req.plan_code.is_none() {
+        return Err("A promo code must grant credits, a plan, or both".to_string());
+    }
+    if req.trial_days.is_some() && req.plan_code.is_none() {
+        return Err("'trial_days' requires a 'plan_code'".to_string());
+    }
+    if req.trial_days.is_some_and(|days| days <= 0) {
+        return Err("'trial_days' must be positive".to_string());
+    }
+    if req.max_redemptions.is_some_and(|max| max <= 0) {
+        return Err("'max_redemptions' must be positive".to_string());
+    }
+    if req.per_user_limit.is_some_and(|limit| limit <= 0) {
+        return Err("'per_user_limit' must be positive".to_string());
+    }
+    if let (Some(from), Some(until)) = (req.valid_from, req.valid_until) {
+        if until <= from {
+            return Err("'valid_until' must be after 'valid_from'".to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Bans a user, or suspends them when `until` is given, and revokes every
+/// token issued so far. Takes effect on the next request: the cached session
+/// carries the status the token extractor checks.
+pub async fn ban_user(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(user_id): Path<i64>,
+    Json(req): Json<BanUserRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'ban_user' request from admin {} for user ID {}",
+        actor, user_id
+    );
+
+    let now = Utc::now();
+    if req.until.is_some_and(|until| until <= now) {
+        let error_message = "Suspension end must be in the future".to_string();
+        error!("{}", error_message);
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, error_message));
+    }
+    let status = match req.until {
+        Some(_) => UserStatus::Suspended,
+        None => UserStatus::Banned,
+    };
+
+    let user = set_user_status(
+        state,
+        &actor,
+        user_id,
+        status,
+        req.until,
+        Some(req.reason),
+        now,
+    )
+    .await?;
+    info!(
+        "User ID {} set to {:?} by admin {}",
+        user_id, user.status, actor
+    );
+
+    let response = Json(UserStatusResponse::from(user)).into_response();
+    Ok(response)
+}
+
+/// Lifts a ban or suspension. Tokens revoked by the ban stay revoked; the
+/// user signs in again.
+pub async fn unban_user(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(user_id): Path<i64>,
+    Json(req): Json<UnbanUserRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'unban_user' request from admin {} for user ID {}",
+        actor, user_id
+    );
+
+    let user = set_user_status(
+        state,
+        &actor,
+        user_id,
+        UserStatus::Active,
+        None,
+        req.reason,
+        Utc::now(),
+    )
+    .await?;
+    info!("User ID {} reactivated by admin {}", user_id, actor);
+
+    let response = Json(UserStatusResponse::from(user)).into_response();
+    Ok(response)
+}
+
+/// Writes the status to the user and session rows, audits it, and refreshes
+/// the cached session once committed. Blocking statuses also revoke tokens.
+async fn set_user_status(
+    state: Arc<ServiceState>,
+    actor: &str,
+    user_id: i64,
+    status: UserStatus,
+    suspended_until: Option<DateTime<Utc>>,
+    reason: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<user::Model, (StatusCode, String)> {
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to set status of user ID {}: {}", user_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let user = repositories::user::set_status(
+        &transaction,
+        user_id,
+        status,
+        suspended_until,
+        reason.clone(),
+        now,
+    )
+    .await
+    .map_err(internal_error)?
+    .ok_or_else(|| {
+        let error_message = format!("User not found: {}", user_id);
+        error!("{}", error_message);
+        (StatusCode::NOT_FOUND, error_message)
+    })?;
+    let session = repositories::session::set_status(
+        &transaction,
+        user_id,
+        status,
+        suspended_until,
+        status != UserStatus::Active,
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let action = match status {
+        UserStatus::Active => "user.unban",
+        UserStatus::Suspended | UserStatus::Banned => "user.ban",
+    };
+    repositories::audit_log::record(
+        &transaction,
+        actor,
+        action,
+        Some(user_id),
+        json!({
+            "status": status,
+            "suspended_until": suspended_until,
+            "reason": reason,
+        }),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    if let Some(session) = session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(state.clone(), SessionKey { user_id }, session),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    Ok(user)
+}
+
+/// Finds users by Telegram user ID or username prefix, read from the replica.
+pub async fn search_users(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Query(query): Query<SearchUsersQuery>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!("Received 'search_users' request from admin {}", actor);
+
+    let username = query
+        .username
+        .as_deref()
+        .map(|username| username.trim().trim_start_matches('@'))
+        .filter(|username| !username.is_empty());
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to search users: {}", e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let users = match (query.user_id, username) {
+        (Some(user_id), None) => {
+            repositories::user::find_by_user_id(state.db_read.as_ref(), user_id)
+                .await
+                .map_err(internal_error)?
+                .into_iter()
+                .collect()
+        }
+        (None, Some(username)) => repositories::user::find_by_username_prefix(
+            state.db_read.as_ref(),
+            username,
+            USER_SEARCH_LIMIT,
+        )
+        .await
+        .map_err(internal_error)?,
+        _ => {
+            let error_message = "Pass exactly one of 'user_id' or 'username'".to_string();
+            error!("{}", error_message);
+            return Err((StatusCode::UNPROCESSABLE_ENTITY, error_message));
+        }
+    };
+
+    let transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "user.search",
+        query.user_id,
+        json!({
+            "user_id": query.user_id,
+            "username": username,
+            "results": users.len(),
+        }),
+        Utc::now(),
+    )
+    .await
+    .map_err(internal_error)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let response = Json(
+        users
+            .into_iter()
+            .map(AdminUserSummary::from)
+            .collect::<Vec<_>>(),
+    )
+    .into_response();
+    Ok(response)
+}
+
+/// Shows a user with their session, current subscription, spendable credits
+/// and latest ledger entries, read from the primary so it reflects the last
+/// write.
+pub async fn get_user(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(user_id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'get_user' request from admin {} for user ID {}",
+        actor, user_id
+    );
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to load user ID {}: {}", user_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let now = Utc::now();
+    let user = find_user(&*transaction, user_id).await?;
+    let session = repositories::session::find_by_user_id(&*transaction, user_id)
+        .await
+        .map_err(internal_error)?;
+    let subscription = repositories::subscription::find_current_by_user_id(&*transaction, user_id)
+        .await
+        .map_err(internal_error)?;
+    let credit_buckets =
+        repositories::credit_bucket::find_spendable_by_user_id(&*transaction, user_id, now)
+            .await
+            .map_err(internal_error)?;
+    let recent_ledger = repositories::credit_ledger::find_recent_by_user_id(
+        &*transaction,
+        user_id,
+        RECENT_LEDGER_ENTRIES,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "user.view",
+        Some(user_id),
+        json!({}),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let response = Json(AdminUserResponse {
+        user: user.into(),
+        session,
+        subscription,
+        credit_buckets: credit_buckets.into_iter().map(Into::into).collect(),
+        recent_ledger,
+    })
+    .into_response();
+    Ok(response)
+}
+
+/// Corrects a user's balance. Added credits go into an adjustment bucket that
+/// doesn't expire; removed credits come out of the buckets like any debit and
+/// may leave the user in debt.
+pub async fn adjust_credits(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(user_id): Path<i64>,
+    Json(req): Json<AdjustCreditsRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'adjust_credits' request from admin {} for user ID {}: {}",
+        actor, user_id, req.amount
+    );
+
+    let reason = req.reason.trim();
+    if req.amount == 0 || reason.is_empty() {
+        let error_message = "An adjustment needs a non-zero amount and a reason".to_string();
+        error!("{}", error_message);
+        return Err((StatusCode::UNPROCESSABLE_ENTITY, error_message));
+    }
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to adjust credits of user ID {}: {}", user_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let now = Utc::now();
+    find_user(&*transaction, user_id).await?;
+
+    let idempotency_key = format!(
+        "adjustment:{}:{}",
+        user_id,
+        req.idempotency_key
+            .clone()
+            .unwrap_or_else(|| Uuid::new_v4().to_string())
+    );
+    let recorded = repositories::credit_ledger::record_with_details(
+        &transaction,
+        user_id,
+        req.amount,
+        LedgerEntryKind::Adjustment,
+        idempotency_key,
+        Some(json!({ "actor": actor, "reason": reason })),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+    if !recorded {
+        let error_message = format!(
+            "Adjustment {} was already applied",
+            req.idempotency_key.unwrap_or_default()
+        );
+        error!("{}", error_message);
+        return Err((StatusCode::CONFLICT, error_message));
+    }
+
+    let session = if req.amount > 0 {
+        utils::credits::grant(
+            &transaction,
+            user_id,
+            req.amount,
+            CreditSource::Adjustment,
+            state
+                .config
+                .billing
+                .credit_expiry(CreditSource::Adjustment, now),
+            now,
+        )
+        .await
+    } else {
+        utils::credits::debit(&transaction, user_id, -req.amount, now).await
+    }
+    .map_err(internal_error)?
+    .ok_or_else(|| internal_error("session record not found".to_string()))?;
+
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "user.credits_adjust",
+        Some(user_id),
+        json!({
+            "amount": req.amount,
+            "reason": reason,
+            "credits_remaining": session.credits_remaining,
+        }),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let credits_remaining = session.credits_remaining;
+    transaction.after_commit(
+        "refresh_session_cache",
+        utils::session::write_after_commit(state.clone(), SessionKey { user_id }, session),
+    );
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+    info!(
+        "Credits of user ID {} adjusted by {} by admin {}",
+        user_id, req.amount, actor
+    );
+
+    let response = Json(AdjustCreditsResponse {
+        user_id,
+        amount: req.amount,
+        credits_remaining,
+    })
+    .into_response();
+    Ok(response)
+}
+
+/// Drops the user's cached session everywhere so the next read comes from the
+/// database, for when a row was fixed outside the service.
+pub async fn expire_session_cache(
+    State(state): State<Arc<ServiceState>>,
+    AdminActor(actor): AdminActor,
+    Path(user_id): Path<i64>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'expire_session_cache' request from admin {} for user ID {}",
+        actor, user_id
+    );
+
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to expire cache of user ID {}: {}", user_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    find_user(&*transaction, user_id).await?;
+    repositories::audit_log::record(
+        &transaction,
+        &actor,
+        "user.cache_expire",
+        Some(user_id),
+        json!({}),
+        Utc::now(),
+    )
+    .await
+    .map_err(internal_error)?;
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let cache = state.cache.as_ref();
+    utils::session::del(cache, &SessionKey { user_id })
+        .await
+        .map_err(internal_error)?;
+    utils::session::del(cache, &MissingSessionKey { user_id })
+        .await
+        .map_err(internal_error)?;
+    state
+        .invalidation
+        .invalidate(&SessionKey { user_id })
+        .await
+        .map_err(internal_error)?;
+    info!(
+        "Session cache of user ID {} expired by admin {}",
+        user_id, actor
+    );
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn find_user(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+) -> Result<user::Model, (StatusCode, String)> {
+    repositories::user::find_by_user_id(db, user_id)
+        .await
+        .map_err(|e| {
+            let error_message = format!("Failed to retrieve user ID {}: {}", user_id, e);
+            error!("{}", error_message);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+        })?
+        .ok_or_else(|| {
+            let error_message = format!("User not found: {}", user_id);
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use axum::body;
+    use metrics_exporter_prometheus::PrometheusBuilder;
+    use sea_orm::{DatabaseBackend, MockDatabase, MockExecResult, Value};
+
+    use super::*;
+    use crate::{
+        client::{cache::CacheClient, memory::MemoryCache, telegram::StubTelegramApi},
+        config::{billing::RefundPolicy, ServiceConfig},
+        entity::{audit_log, credit_bucket, credit_ledger, payment, session},
+        utils::{invalidation::InvalidationBus, singleflight::SingleFlight},
+    };
+
+    const USER_ID: i64 = 42;
+    const CHARGE_ID: &str = "charge-1";
+
+    fn payment(status: PaymentStatus, credits_granted: i64) -> payment::Model {
+        let now = Utc::now();
+        payment::Model {
+            id: Uuid::new_v4(),
+            user_id: USER_ID,
+            plan_id: Some(Uuid::new_v4()),
+            telegram_payment_charge_id: CHARGE_ID.to_string(),
+            provider_payment_charge_id: String::new(),
+            currency: "XTR".to_string(),
+            total_amount: 100,
+            invoice_payload: String::new(),
+            status,
+            subscription_id: None,
+            credits_granted,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn session(credits_remaining: i64, version: i64) -> session::Model {
+        let now = Utc::now();
+        session::Model {
+            id: Uuid::new_v4(),
+            user_id: USER_ID,
+            subscription_status: false,
+            credits_remaining,
+            last_active_timestamp: now.timestamp(),
+            preferences: json!({}),
+            session_metadata: json!({}),
+            last_refill_at: None,
+            status: UserStatus::Active,
+            suspended_until: None,
+            tokens_revoked_at: None,
+            version,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn user(credits_remaining: i64) -> user::Model {
+        let now = Utc::now();
+        user::Model {
+            id: Uuid::new_v4(),
+            user_id: USER_ID,
+            username: None,
+            total_credits: 50,
+            credits_remaining,
+            subscription_status: false,
+            status: UserStatus::Active,
+            suspended_until: None,
+            status_reason: None,
+            version: 2,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    fn ledger_entry(amount: i64) -> credit_ledger::Model {
+        credit_ledger::Model {
+            id: Uuid::new_v4(),
+            user_id: USER_ID,
+            amount,
+            kind: LedgerEntryKind::Refund,
+            idempotency_key: format!("refund:{}", CHARGE_ID),
+            details: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn audit_entry() -> audit_log::Model {
+        audit_log::Model {
+            id: Uuid::new_v4(),
+            actor: "alice".to_string(),
+            action: "payment.refund".to_string(),
+            target_user_id: Some(USER_ID),
+            details: json!({}),
+            created_at: Utc::now(),
+        }
+    }
+
+    fn updated(rows_affected: u64) -> MockExecResult {
+        MockExecResult {
+            last_insert_id: 0,
+            rows_affected,
+        }
+    }
+
+    /// A payment found in `status`, claimed for the refund unless it already
+    /// is pending.
+    fn claimed(status: PaymentStatus) -> MockDatabase {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![payment(status, 50)]]);
+        if status == PaymentStatus::RefundPending {
+            return db;
+        }
+        db.append_exec_results([updated(1)])
+            .append_query_results([vec![audit_entry()]])
+    }
+
+    /// Adds the results of reversing a 50 credit payment without a
+    /// subscription or buckets, taking `credits_reversed` from a balance of
+    /// `balance`.
+    fn reversed(db: MockDatabase, balance: i64, credits_reversed: i64) -> MockDatabase {
+        let balance_after = balance - credits_reversed;
+        db.append_exec_results([updated(1)])
+            .append_query_results([vec![session(balance, 1)]])
+            .append_query_results([vec![ledger_entry(-credits_reversed)]])
+            .append_query_results([Vec::<credit_bucket::Model>::new()])
+            .append_query_results([vec![BTreeMap::from([("num_items", Value::from(0i64))])]])
+            .append_query_results([vec![session(balance_after, 2)]])
+            .append_query_results([vec![user(balance_after)]])
+            .append_query_results([vec![audit_entry()]])
+    }
+
+    fn state(
+        db: MockDatabase,
+        policy: RefundPolicy,
+        telegram: Arc<StubTelegramApi>,
+    ) -> Arc<ServiceState> {
+        let mut config = ServiceConfig::default();
+        config.billing.refund_policy = policy;
+        let db = Arc::new(db.into_connection());
+        let cache: Arc<CacheClient> = MemoryCache::new();
+        Arc::new(ServiceState {
+            config: Arc::new(config),
+            db: db.clone(),
+            db_read: db,
+            invalidation: InvalidationBus::new(cache.clone(), "invalidations".to_string()),
+            cache,
+            metrics: PrometheusBuilder::new().build_recorder().handle(),
+            session_loads: Arc::new(SingleFlight::default()),
+            telegram,
+        })
+    }
+
+    async fn refund(state: &Arc<ServiceState>) -> Result<serde_json::Value, (StatusCode, String)> {
+        let response = refund_payment(
+            State(state.clone()),
+            AdminActor("alice".to_string()),
+            Path(CHARGE_ID.to_string()),
+            Json(RefundPaymentRequest {
+                reason: "requested by user".to_string(),
+            }),
+        )
+        .await?
+        .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        Ok(serde_json::from_slice(&bytes).unwrap())
+    }
+
+    /// Everything the mock database was asked to run. Takes the state, which
+    /// nothing else may still hold, to get at the log.
+    fn transaction_log(state: Arc<ServiceState>) -> String {
+        let state = Arc::into_inner(state).expect("service state is still shared");
+        drop(state.db_read);
+        let log = Arc::into_inner(state.db)
+            .expect("database is still shared")
+            .into_transaction_log();
+        format!("{:?}", log)
+    }
+
+    async fn cached_balance(state: &ServiceState) -> Option<i64> {
+        utils::session::get(state.cache.as_ref(), &SessionKey { user_id: USER_ID })
+            .await
+            .unwrap()
+            .map(|session| session.credits_remaining)
+    }
+
+    #[tokio::test]
+    async fn refund_payment_clamps_at_zero() {
+        let telegram = Arc::new(StubTelegramApi::new(Ok(())));
+        let db = reversed(claimed(PaymentStatus::Paid), 30, 30);
+        let state = state(db, RefundPolicy::Clamp, telegram.clone());
+
+        let body = refund(&state).await.unwrap();
+        assert_eq!(body["credits_reversed"], 30);
+        assert_eq!(body["subscription_canceled"], false);
+        assert_eq!(
+            *telegram.refunds.lock().unwrap(),
+            vec![(USER_ID, CHARGE_ID.to_string())]
+        );
+        assert_eq!(cached_balance(&state).await, Some(0));
+        let log = transaction_log(state);
+        assert!(log.contains("FOR UPDATE"));
+        assert_eq!(log.matches("\"COMMIT\"").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn refund_payment_leaves_debt() {
+        let telegram = Arc::new(StubTelegramApi::new(Ok(())));
+        let db = reversed(claimed(PaymentStatus::Paid), 30, 50);
+        let state = state(db, RefundPolicy::Debt, telegram);
+
+        let body = refund(&state).await.unwrap();
+        assert_eq!(body["credits_reversed"], 50);
+        assert_eq!(cached_balance(&state).await, Some(-20));
+        assert_eq!(transaction_log(state).matches("\"COMMIT\"").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn refund_payment_rejects_refunded_payment() {
+        let telegram = Arc::new(StubTelegramApi::new(Ok(())));
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![payment(PaymentStatus::Refunded, 50)]]);
+        let state = state(db, RefundPolicy::Clamp, telegram.clone());
+
+        let (status, _) = refund(&state).await.unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(telegram.refunds.lock().unwrap().is_empty());
+        assert!(!transaction_log(state).contains("\"COMMIT\""));
+    }
+
+    #[tokio::test]
+    async fn refund_payment_rejects_concurrently_claimed_payment() {
+        let telegram = Arc::new(StubTelegramApi::new(Ok(())));
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![payment(PaymentStatus::Paid, 50)]])
+            .append_exec_results([updated(0)]);
+        let state = state(db, RefundPolicy::Clamp, telegram.clone());
+
+        let (status, _) = refund(&state).await.unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(telegram.refunds.lock().unwrap().is_empty());
+        assert!(transaction_log(state).contains("\"ROLLBACK\""));
+    }
+
+    #[tokio::test]
+    async fn refund_payment_keeps_payment_pending_when_telegram_fails() {
+        let telegram = Arc::new(StubTelegramApi::new(Err("CHARGE_NOT_FOUND".to_string())));
+        let state = state(
+            claimed(PaymentStatus::Paid),
+            RefundPolicy::Clamp,
+            telegram.clone(),
+        );
+
+        let (status, _) = refund(&state).await.unwrap_err();
+        assert_eq!(status, StatusCode::BAD_GATEWAY);
+        assert_eq!(telegram.refunds.lock().unwrap().len(), 1);
+        assert_eq!(cached_balance(&state).await, None);
+        // Only the claim was committed; nothing was reversed or locked.
+        let log = transaction_log(state);
+        assert!(log.contains("payment.refund_requested"));
+        assert_eq!(log.matches("\"COMMIT\"").count(), 1);
+        assert!(!log.contains("FOR UPDATE"));
+    }
+
+    #[tokio::test]
+    async fn refund_payment_resumes_pending_refund() {
+        let telegram = Arc::new(StubTelegramApi::new(Ok(())));
+        let db = reversed(claimed(PaymentStatus::RefundPending), 30, 30);
+        let state = state(db, RefundPolicy::Clamp, telegram.clone());
+
+        let body = refund(&state).await.unwrap();
+        assert_eq!(body["credits_reversed"], 30);
+        assert_eq!(telegram.refunds.lock().unwrap().len(), 1);
+        let log = transaction_log(state);
+        assert!(!log.contains("payment.refund_requested"));
+        assert_eq!(log.matches("\"COMMIT\"").count(), 1);
+    }
+
+    #[tokio::test]
+    async fn refund_payment_rejects_payment_reversed_meanwhile() {
+        let telegram = Arc::new(StubTelegramApi::new(Ok(())));
+        let db = claimed(PaymentStatus::RefundPending).append_exec_results([updated(0)]);
+        let state = state(db, RefundPolicy::Clamp, telegram.clone());
+
+        let (status, _) = refund(&state).await.unwrap_err();
+        assert_eq!(status, StatusCode::CONFLICT);
+        assert!(transaction_log(state).contains("\"ROLLBACK\""));
+    }
+}
@@ -0,0 +1,9 @@
+use std::sync::Arc;
+
+use axum::{extract::State, response::IntoResponse};
+
+use crate::ServiceState;
+
+pub async fn get_metrics(State(state): State<Arc<ServiceState>>) -> impl IntoResponse {
+    state.metrics.render()
+}
@@ -1,2 +1,9 @@
+pub mod admin;
+pub mod metrics;
+pub mod promo;
+pub mod quota;
+pub mod referral;
 pub mod session;
+pub mod telegram;
+pub mod usage;
 pub mod user;
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use chrono::{Duration, Utc};
+use sea_orm::Set;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::{
+    dto::{request::RedeemPromoRequest, response::RedeemPromoResponse},
+    entity::{
+        credit_bucket::CreditSource, credit_ledger::LedgerEntryKind, promo_code, promo_redemption,
+        subscription::SubscriptionRenewal,
+    },
+    repositories,
+    utils::{self, jwt::UserClaims, session::SessionKey, transaction::UnitOfWork},
+    ServiceState,
+};
+
+pub async fn redeem(
+    State(state): State<Arc<ServiceState>>,
+    user: UserClaims,
+    Json(req): Json<RedeemPromoRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!("Received 'redeem' promo request for user ID: {}", user.uid);
+
+    let internal_error = |e: String| {
+        let error_message = format!(
+            "Failed to redeem promo code for user ID {}: {}",
+            user.uid, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let promo = repositories::promo_code::find_by_code(&*transaction, req.code.trim())
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let error_message = "Unknown promo code".to_string();
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
+        })?;
+
+    let now = Utc::now();
+    // Claiming first takes the code's row lock, so the per-user count below
+    // sees every redemption committed before ours.
+    if !repositories::promo_code::claim_redemption(&transaction, promo.id, now)
+        .await
+        .map_err(internal_error)?
+    {
+        let error_message = format!("Promo code {} {}", promo.code, unavailable_reason(&promo));
+        error!("{}", error_message);
+        return Err((StatusCode::CONFLICT, error_message));
+    }
+    let redeemed =
+        repositories::promo_redemption::count_by_user_id(&*transaction, promo.id, user.uid)
+            .await
+            .map_err(internal_error)?;
+    if redeemed >= promo.per_user_limit as u64 {
+        let error_message = format!("Promo code {} was already redeemed", promo.code);
+        error!("{}", error_message);
+        return Err((StatusCode::CONFLICT, error_message));
+    }
+
+    let mut plan_code = None;
+    let mut subscription = None;
+    if let Some(plan_id) = promo.plan_id {
+        // A trial must not replace or cut short a subscription the user has
+        // already paid for.
+        if repositories::subscription::is_active_by_user_id(&*transaction, user.uid, now)
+            .await
+            .map_err(internal_error)?
+        {
+            let error_message = format!(
+                "Promo code {} cannot be redeemed during an active subscription",
+                promo.code
+            );
+            error!("{}", error_message);
+            return Err((StatusCode::CONFLICT, error_message));
+        }
+        let plan = repositories::plan::find_by_id(&*transaction, plan_id)
+            .await
+            .map_err(internal_error)?
+            .ok_or_else(|| internal_error(format!("Plan {} not found", plan_id)))?;
+        let period_end = promo
+            .trial_days
+            .map(|days| now + Duration::days(days.into()));
+        subscription = Some(
+            utils::billing::start_subscription(
+                &transaction,
+                user.uid,
+                &plan,
+                period_end,
+                SubscriptionRenewal::Never,
+                now,
+            )
+            .await
+            .map_err(internal_error)?,
+        );
+        plan_code = Some(plan.code);
+    }
+
+    let redemption = repositories::promo_redemption::save(
+        &transaction,
+        promo_redemption::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            promo_code_id: Set(promo.id),
+            user_id: Set(user.uid),
+            credits_granted: Set(promo.credits),
+            subscription_id: Set(subscription.as_ref().map(|subscription| subscription.id)),
+            created_at: Set(now),
+        },
+    )
+    .await
+    .map_err(internal_error)?;
+
+    if promo.credits != 0 {
+        repositories::credit_ledger::record(
+            &transaction,
+            user.uid,
+            promo.credits,
+            LedgerEntryKind::Promo,
+            format!("promo:{}", redemption.id),
+            now,
+        )
+        .await
+        .map_err(internal_error)?;
+    }
+
+    let session = utils::credits::grant(
+        &transaction,
+        user.uid,
+        promo.credits,
+        CreditSource::Promo,
+        state.config.billing.credit_expiry(CreditSource::Promo, now),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+    if let Some(session) = session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey { user_id: user.uid },
+                session,
+            ),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    metrics::counter!("promo_redemptions_total").increment(1);
+    info!("User ID {} redeemed promo code {}", user.uid, promo.code);
+
+    let response = Json(RedeemPromoResponse {
+        code: promo.code,
+        credits_granted: promo.credits,
+        plan_code,
+        subscription_period_end: subscription.map(|subscription| subscription.current_period_end),
+    })
+    .into_response();
+    Ok(response)
+}
+
+fn unavailable_reason(promo: &promo_code::Model) -> &'static str {
+    let now = Utc::now();
+    if !promo.is_active {
+        "has been deactivated"
+    } else if promo.valid_from.is_some_and(|from| from > now) {
+        "is not valid yet"
+    } else if promo.valid_until.is_some_and(|until| until <= now) {
+        "has expired"
+    } else {
+        "has been fully redeemed"
+    }
+}
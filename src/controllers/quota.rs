@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::Utc;
+use tracing::{error, info};
+
+use crate::{dto::request::ConsumeQuotaRequest, utils, ServiceState};
+
+/// Checks a user's quota and, if there is room, takes `cost` units of it.
+/// Answers 429 with the quota state and a `Retry-After` when there isn't.
+pub async fn consume_quota(
+    State(state): State<Arc<ServiceState>>,
+    Json(req): Json<ConsumeQuotaRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'consume_quota' request for quota {} of user ID {}",
+        req.quota, req.user_id
+    );
+
+    let definition = state.config.quota.quotas.get(&req.quota).ok_or_else(|| {
+        let error_message = format!("Unknown quota: {}", req.quota);
+        error!("{}", error_message);
+        (StatusCode::NOT_FOUND, error_message)
+    })?;
+
+    let internal_error = |e: String| {
+        let error_message = format!(
+            "Failed to check quota {} for user ID {}: {}",
+            req.quota, req.user_id, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let session = utils::session::get_session_by_user_id(state.clone(), req.user_id)
+        .await
+        .map_err(internal_error)?;
+    let tier = utils::quota::tier_for(
+        state.db_read.as_ref(),
+        req.user_id,
+        session.subscription_status,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let now = Utc::now();
+    let quota = utils::quota::consume(
+        state.cache.as_ref(),
+        &req.quota,
+        definition,
+        &tier,
+        req.user_id,
+        req.cost,
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    if quota.allowed {
+        return Ok(Json(quota).into_response());
+    }
+
+    metrics::counter!("quota_rejections_total", "quota" => req.quota.clone()).increment(1);
+    info!(
+        "Quota {} exhausted for user ID {} on tier {}",
+        req.quota, req.user_id, tier
+    );
+    let retry_after = quota
+        .reset_at
+        .map(|reset_at| (reset_at - now).num_seconds().max(1))
+        .unwrap_or(1);
+    let response = (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(quota),
+    )
+        .into_response();
+    Ok(response)
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use tracing::{error, info};
+
+use crate::{
+    dto::response::ReferralsResponse,
+    repositories,
+    utils::{self, jwt::UserClaims},
+    ServiceState,
+};
+
+pub async fn get_referrals(
+    State(state): State<Arc<ServiceState>>,
+    user: UserClaims,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!("Received 'get_referrals' request for user ID: {}", user.uid);
+
+    let internal_error = |e: String| {
+        let error_message = format!(
+            "Failed to retrieve referrals for user ID {}: {}",
+            user.uid, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let invite_count = repositories::referral::count_by_referrer(state.db_read.as_ref(), user.uid)
+        .await
+        .map_err(internal_error)?;
+    let credits_earned =
+        repositories::referral::sum_referrer_credits(state.db_read.as_ref(), user.uid)
+            .await
+            .map_err(internal_error)?;
+
+    let response = Json(ReferralsResponse {
+        referral_code: utils::referral::code_for(user.uid),
+        invite_link: utils::referral::invite_link(&state.config.referral, user.uid),
+        invite_count,
+        credits_earned,
+    })
+    .into_response();
+    Ok(response)
+}
@@ -1,19 +1,39 @@
 use std::sync::Arc;
 
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use chrono::Utc;
-use sea_orm::{ActiveModelTrait, Set, TransactionTrait};
-use tracing::{error, info};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseTransaction, Set};
+use serde_json::json;
+use tracing::{error, info, warn};
+use uuid::Uuid;
 
 use crate::{
-    dto::{request::SetSessionRequest, response::GetSessionResponse},
-    entity, repositories,
-    utils::{self, jwt::UserClaims, session::SessionKey},
+    dto::{
+        request::SetSessionRequest,
+        response::GetSessionResponse,
+        session_data::{Preferences, SchemaDocument, SessionMetadata},
+    },
+    entity::{
+        self,
+        credit_bucket::CreditSource,
+        credit_ledger::LedgerEntryKind,
+        plan::BillingPeriod,
+        subscription::{SubscriptionRenewal, SubscriptionStatus},
+    },
+    repositories,
+    utils::{self, etag::etag, jwt::UserClaims, session::SessionKey, transaction::UnitOfWork},
     ServiceState,
 };
 
 pub async fn set_session(
     State(state): State<Arc<ServiceState>>,
+    headers: HeaderMap,
     Json(req): Json<SetSessionRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     info!(
@@ -21,7 +41,9 @@ pub async fn set_session(
         req.user_id
     );
 
-    let transaction = state.db.begin().await.map_err(|e| {
+    let expected_version = utils::etag::expected_version(&headers, req.expected_version)?;
+
+    let mut transaction = UnitOfWork::begin(&state.db).await.map_err(|e| {
         let error_message = format!(
             "Failed to start a database transaction for user ID {}: {}",
             req.user_id, e
@@ -30,7 +52,7 @@ pub async fn set_session(
         (StatusCode::INTERNAL_SERVER_ERROR, error_message)
     })?;
 
-    let session_data = utils::session::get_session_by_user_id(state.clone(), req.user_id)
+    let session_data = repositories::session::find_by_user_id(&*transaction, req.user_id)
         .await
         .map_err(|e| {
             let error_message = format!(
@@ -39,62 +61,150 @@ pub async fn set_session(
             );
             error!("{}", error_message);
             (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+        })?
+        .ok_or_else(|| {
+            let error_message = format!("Session record not found for user ID: {}", req.user_id);
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
         })?;
 
-    let updated_model = entity::session::ActiveModel {
-        id: Set(session_data.id),
-        user_id: Set(session_data.user_id),
-        subscription_status: Set({
-            if req.subscription_status.is_some() {
-                req.subscription_status.unwrap()
-            } else if req.credits_remaining.is_some() && req.credits_remaining.unwrap() == 0 {
-                false
-            } else {
-                session_data.subscription_status
-            }
-        }),
-        credits_remaining: Set({
-            if req.credits_remaining.is_some() {
-                req.credits_remaining.unwrap()
-            } else if req.subscription_status.is_some() && req.subscription_status.unwrap() == true {
-                session_data.credits_remaining + state.config.charged_credit
-            } else {
-                session_data.credits_remaining
-            }
-        }),
-        last_active_timestamp: Set(session_data.last_active_timestamp),
-        preferences: Set({
-            if req.preferences.is_some() {
-                req.preferences.unwrap()
-            } else {
-                session_data.preferences
-            }
-        }),
-        session_metadata: Set({
-            if req.session_metadata.is_some() {
-                req.session_metadata.unwrap()
-            } else {
-                session_data.session_metadata
-            }
-        }),
-        created_at: Set(session_data.created_at),
-        updated_at: Set(Utc::now()),
-    };
+    if let Some(expected_version) = expected_version {
+        if expected_version != session_data.version {
+            let error_message = format!(
+                "Session for user ID {} is at version {}, not {}",
+                req.user_id, session_data.version, expected_version
+            );
+            error!("{}", error_message);
+            return Err((StatusCode::CONFLICT, error_message));
+        }
+    }
 
-    let updated_data: entity::session::Model =
-        updated_model.update(&transaction).await.map_err(|e| {
+    let now = Utc::now();
+    let granted_credits =
+        apply_subscription_change(&transaction, &req, state.config.charged_credit, now).await?;
+    let subscription_status =
+        utils::billing::derived_subscription_status(&*transaction, req.user_id, now)
+            .await
+            .map_err(|e| {
+                let error_message = format!(
+                    "Failed to check subscription for user ID {}: {}",
+                    req.user_id, e
+                );
+                error!("{}", error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            })?
+            .unwrap_or_else(|| legacy_subscription_status(&req, &session_data));
+
+    // A directly set balance is applied to the buckets as a debit or top-up
+    // and recorded as an adjustment, keyed by the version it replaces.
+    if let Some(credits_remaining) = req.credits_remaining {
+        let delta = credits_remaining - session_data.credits_remaining;
+        if delta != 0 {
+            repositories::credit_ledger::record_with_details(
+                &transaction,
+                req.user_id,
+                delta,
+                LedgerEntryKind::Adjustment,
+                format!(
+                    "adjustment:{}:session:{}",
+                    req.user_id, session_data.version
+                ),
+                Some(json!({ "credits_remaining": credits_remaining })),
+                now,
+            )
+            .await
+            .map_err(|e| {
+                let error_message = format!(
+                    "Failed to record balance change for user ID {}: {}",
+                    req.user_id, e
+                );
+                error!("{}", error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            })?;
+        }
+        let outcome = if delta < 0 {
+            utils::credits::consume(&transaction, req.user_id, -delta, now).await
+        } else {
+            utils::credits::add_bucket(
+                &transaction,
+                req.user_id,
+                delta,
+                CreditSource::Adjustment,
+                None,
+                now,
+            )
+            .await
+        };
+        outcome.map_err(|e| {
             let error_message = format!(
-                "Error updating session data for user ID {}: {}",
+                "Failed to update credit buckets for user ID {}: {}",
                 req.user_id, e
             );
             error!("{}", error_message);
             (StatusCode::INTERNAL_SERVER_ERROR, error_message)
         })?;
+    }
+
+    let preferences = resolve_json_field::<Preferences>(
+        session_data.preferences.clone(),
+        req.preferences,
+        req.preferences_patch.as_ref(),
+    )?;
+    let session_metadata = resolve_json_field::<SessionMetadata>(
+        session_data.session_metadata.clone(),
+        req.session_metadata,
+        req.session_metadata_patch.as_ref(),
+    )?;
+
+    let updated_model = entity::session::ActiveModel {
+        id: Set(session_data.id),
+        user_id: Set(session_data.user_id),
+        subscription_status: Set(subscription_status),
+        credits_remaining: Set(req
+            .credits_remaining
+            .unwrap_or(session_data.credits_remaining)
+            + granted_credits),
+        last_active_timestamp: Set(session_data.last_active_timestamp),
+        preferences: Set(preferences),
+        session_metadata: Set(session_metadata),
+        last_refill_at: Set(session_data.last_refill_at),
+        status: Set(session_data.status),
+        suspended_until: Set(session_data.suspended_until),
+        tokens_revoked_at: Set(session_data.tokens_revoked_at),
+        version: Set(session_data.version),
+        created_at: Set(session_data.created_at),
+        updated_at: Set(now),
+    };
 
-    if session_data.credits_remaining != updated_data.credits_remaining
+    let updated_data = repositories::session::update_if_version(
+        &transaction,
+        session_data.id,
+        updated_model,
+        session_data.version,
+    )
+    .await
+    .map_err(|e| {
+        let error_message = format!(
+            "Error updating session data for user ID {}: {}",
+            req.user_id, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    })?
+    .ok_or_else(|| {
+        let error_message = format!(
+            "Session for user ID {} was modified concurrently",
+            req.user_id
+        );
+        error!("{}", error_message);
+        (StatusCode::CONFLICT, error_message)
+    })?;
+
+    if granted_credits != 0
+        || session_data.credits_remaining != updated_data.credits_remaining
         || session_data.subscription_status != updated_data.subscription_status
     {
-        let user_model = repositories::user::find_by_user_id(&transaction, req.user_id)
+        let user_model = repositories::user::find_by_user_id(&*transaction, req.user_id)
             .await
             .map_err(|e| {
                 let error_message = format!(
@@ -111,51 +221,56 @@ pub async fn set_session(
         }
 
         let user_model = user_model.unwrap();
-        let mut updated_credit_remaining = user_model.credits_remaining;
-        let mut updated_subscription_status = user_model.subscription_status;
-        let mut updated_total_credits = user_model.total_credits;
-
-        if session_data.subscription_status != updated_data.subscription_status {
-            updated_total_credits += state.config.charged_credit;
-            updated_subscription_status = true;
-        }
-        if session_data.credits_remaining != updated_data.credits_remaining {
-            updated_credit_remaining = updated_data.credits_remaining;
-        }
-
         let updated_user = entity::user::ActiveModel {
             id: Set(user_model.id),
             user_id: Set(user_model.user_id),
-            total_credits: Set(updated_total_credits),
-            credits_remaining: Set(updated_credit_remaining),
-            subscription_status: Set(updated_subscription_status),
+            total_credits: Set(user_model.total_credits + granted_credits),
+            credits_remaining: Set(updated_data.credits_remaining),
+            subscription_status: Set(updated_data.subscription_status),
+            username: Set(user_model.username),
+            status: Set(user_model.status),
+            suspended_until: Set(user_model.suspended_until),
+            status_reason: Set(user_model.status_reason),
+            version: Set(user_model.version),
             created_at: Set(user_model.created_at),
-            updated_at: Set(Utc::now()),
+            updated_at: Set(now),
         };
 
-        updated_user.update(&transaction).await.map_err(|e| {
+        repositories::user::update_if_version(
+            &transaction,
+            user_model.id,
+            updated_user,
+            user_model.version,
+        )
+        .await
+        .map_err(|e| {
             let error_message = format!(
                 "Error updating user data for user ID {}: {}",
                 req.user_id, e
             );
             error!("{}", error_message);
             (StatusCode::INTERNAL_SERVER_ERROR, error_message)
-        })?;
-    }
-
-    let session_key = SessionKey {
-        user_id: req.user_id,
-    };
-    utils::session::set(&state.redis, (&session_key, &updated_data))
-        .await
-        .map_err(|e| {
+        })?
+        .ok_or_else(|| {
             let error_message = format!(
-                "Failed to update session data in Redis for user ID {}: {}",
-                req.user_id, e
+                "User record for user ID {} was modified concurrently",
+                req.user_id
             );
             error!("{}", error_message);
-            (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            (StatusCode::CONFLICT, error_message)
         })?;
+    }
+
+    transaction.after_commit(
+        "refresh_session_cache",
+        utils::session::write_after_commit(
+            state.clone(),
+            SessionKey {
+                user_id: req.user_id,
+            },
+            updated_data.clone(),
+        ),
+    );
 
     transaction.commit().await.map_err(|e| {
         let error_message = format!(
@@ -171,7 +286,11 @@ pub async fn set_session(
         req.user_id
     );
 
-    let response = Json(GetSessionResponse::default()).into_response();
+    let response = (
+        [(header::ETAG, etag(updated_data.version))],
+        Json(session_response(&state, updated_data).await?),
+    )
+        .into_response();
     Ok(response)
 }
 
@@ -197,12 +316,394 @@ pub async fn get_session(
         user.uid
     );
 
-    let response = Json(GetSessionResponse {
-        subscription_status: session_model.subscription_status,
-        credits_remaining: session_model.credits_remaining,
-        preferences: session_model.preferences.clone(),
-        session_metadata: session_model.session_metadata.clone(),
-    })
-    .into_response();
+    let response = (
+        [(header::ETAG, etag(session_model.version))],
+        Json(session_response(&state, session_model).await?),
+    )
+        .into_response();
+    Ok(response)
+}
+
+pub async fn patch_preferences(
+    State(state): State<Arc<ServiceState>>,
+    user: UserClaims,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'patch_preferences' request for user ID: {}",
+        user.uid
+    );
+
+    let patch = utils::merge_patch::parse_body(&headers, &body)?;
+
+    let expected_version = utils::etag::parse_if_match(&headers)?;
+
+    let mut transaction = UnitOfWork::begin(&state.db).await.map_err(|e| {
+        let error_message = format!(
+            "Failed to start a database transaction for user ID {}: {}",
+            user.uid, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    })?;
+
+    let session_data = repositories::session::find_by_user_id(&*transaction, user.uid)
+        .await
+        .map_err(|e| {
+            let error_message = format!(
+                "Failed to retrieve session data for user ID {}: {}",
+                user.uid, e
+            );
+            error!("{}", error_message);
+            (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+        })?
+        .ok_or_else(|| {
+            let error_message = format!("Session record not found for user ID: {}", user.uid);
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
+        })?;
+
+    if let Some(expected_version) = expected_version {
+        if expected_version != session_data.version {
+            let error_message = format!(
+                "Session for user ID {} is at version {}, not {}",
+                user.uid, session_data.version, expected_version
+            );
+            error!("{}", error_message);
+            return Err((StatusCode::CONFLICT, error_message));
+        }
+    }
+
+    let preferences =
+        resolve_json_field::<Preferences>(session_data.preferences, None, Some(&patch))?;
+
+    let updated_model = entity::session::ActiveModel {
+        preferences: Set(preferences),
+        updated_at: Set(Utc::now()),
+        ..Default::default()
+    };
+
+    let updated_data = repositories::session::update_if_version(
+        &transaction,
+        session_data.id,
+        updated_model,
+        session_data.version,
+    )
+    .await
+    .map_err(|e| {
+        let error_message = format!("Error updating preferences for user ID {}: {}", user.uid, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    })?
+    .ok_or_else(|| {
+        let error_message = format!("Session for user ID {} was modified concurrently", user.uid);
+        error!("{}", error_message);
+        (StatusCode::CONFLICT, error_message)
+    })?;
+
+    transaction.after_commit(
+        "refresh_session_cache",
+        utils::session::write_after_commit(
+            state.clone(),
+            SessionKey { user_id: user.uid },
+            updated_data.clone(),
+        ),
+    );
+
+    transaction.commit().await.map_err(|e| {
+        let error_message = format!(
+            "Failed to commit transaction for user ID {}: {}",
+            user.uid, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    })?;
+
+    info!("Successfully patched preferences for user ID: {}", user.uid);
+
+    let response = (
+        [(header::ETAG, etag(updated_data.version))],
+        Json(session_response(&state, updated_data).await?),
+    )
+        .into_response();
     Ok(response)
 }
+
+/// The stored subscription flag after a `set_session` request, for users
+/// without subscription rows. Mirrors how the flag was set before plans:
+/// taken from the request, and cleared by zeroing the balance.
+fn legacy_subscription_status(req: &SetSessionRequest, session: &entity::session::Model) -> bool {
+    match (req.subscription_status, req.credits_remaining) {
+        (Some(subscription_status), _) => subscription_status,
+        (None, Some(0)) => false,
+        (None, _) => session.subscription_status,
+    }
+}
+
+/// Applies the subscription fields of a `set_session` request and returns the
+/// credits granted by a newly started plan, or `charged_credit` when a user
+/// without subscription rows is flagged as subscribed without a plan.
+async fn apply_subscription_change(
+    transaction: &DatabaseTransaction,
+    req: &SetSessionRequest,
+    charged_credit: i64,
+    now: DateTime<Utc>,
+) -> Result<i64, (StatusCode, String)> {
+    let internal_error = |e: String| {
+        let error_message = format!(
+            "Failed to update subscription for user ID {}: {}",
+            req.user_id, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut granted_credits = 0;
+    match (&req.plan_code, req.subscription_status) {
+        (None, Some(true)) => {
+            match utils::billing::derived_subscription_status(transaction, req.user_id, now)
+                .await
+                .map_err(internal_error)?
+            {
+                Some(true) => {}
+                Some(false) => {
+                    let error_message = format!(
+                        "User ID {} has no active subscription; pass a 'plan_code' to start one",
+                        req.user_id
+                    );
+                    error!("{}", error_message);
+                    return Err((StatusCode::CONFLICT, error_message));
+                }
+                None if req.credits_remaining.is_none() => {
+                    repositories::credit_ledger::record(
+                        transaction,
+                        req.user_id,
+                        charged_credit,
+                        LedgerEntryKind::SubscriptionStart,
+                        format!("subscription:legacy:{}", Uuid::new_v4()),
+                        now,
+                    )
+                    .await
+                    .map_err(internal_error)?;
+                    utils::credits::add_bucket(
+                        transaction,
+                        req.user_id,
+                        charged_credit,
+                        CreditSource::Subscription,
+                        None,
+                        now,
+                    )
+                    .await
+                    .map_err(internal_error)?;
+                    granted_credits = charged_credit;
+                }
+                None => {}
+            }
+        }
+        (Some(_), Some(false)) => {
+            let error_message =
+                "'plan_code' cannot be combined with 'subscription_status: false'".to_string();
+            error!("{}", error_message);
+            return Err((StatusCode::BAD_REQUEST, error_message));
+        }
+        (Some(plan_code), _) => {
+            let plan = repositories::plan::find_active_by_code(transaction, plan_code)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(|| {
+                    let error_message = format!("No purchasable plan with code '{}'", plan_code);
+                    error!("{}", error_message);
+                    (StatusCode::UNPROCESSABLE_ENTITY, error_message)
+                })?;
+            if plan.period == BillingPeriod::OneTime {
+                let error_message = format!("Plan '{}' is not a subscription plan", plan_code);
+                error!("{}", error_message);
+                return Err((StatusCode::UNPROCESSABLE_ENTITY, error_message));
+            }
+            let subscription = utils::billing::start_subscription(
+                transaction,
+                req.user_id,
+                &plan,
+                None,
+                SubscriptionRenewal::Auto,
+                now,
+            )
+            .await
+            .map_err(internal_error)?;
+            repositories::credit_ledger::record(
+                transaction,
+                req.user_id,
+                plan.credits_per_period,
+                LedgerEntryKind::SubscriptionStart,
+                format!("subscription:{}:start", subscription.id),
+                now,
+            )
+            .await
+            .map_err(internal_error)?;
+            utils::credits::add_bucket(
+                transaction,
+                req.user_id,
+                plan.credits_per_period,
+                CreditSource::Subscription,
+                Some(subscription.current_period_end),
+                now,
+            )
+            .await
+            .map_err(internal_error)?;
+            granted_credits = plan.credits_per_period;
+        }
+        (None, Some(false)) => {
+            repositories::subscription::end_all_by_user_id(
+                transaction,
+                req.user_id,
+                SubscriptionStatus::Canceled,
+                now,
+            )
+            .await
+            .map_err(internal_error)?;
+        }
+        (None, None) => {}
+    }
+
+    if let Some(cancel_at_period_end) = req.cancel_at_period_end {
+        let subscription =
+            repositories::subscription::find_current_by_user_id(transaction, req.user_id)
+                .await
+                .map_err(internal_error)?
+                .ok_or_else(|| {
+                    let error_message =
+                        format!("User ID {} has no active subscription", req.user_id);
+                    error!("{}", error_message);
+                    (StatusCode::CONFLICT, error_message)
+                })?;
+        if !cancel_at_period_end && subscription.renewal != SubscriptionRenewal::Auto {
+            let error_message = format!(
+                "Subscription of user ID {} does not renew automatically",
+                req.user_id
+            );
+            error!("{}", error_message);
+            return Err((StatusCode::CONFLICT, error_message));
+        }
+        repositories::subscription::set_cancel_at_period_end(
+            transaction,
+            subscription.id,
+            cancel_at_period_end,
+            now,
+        )
+        .await
+        .map_err(internal_error)?;
+    }
+
+    Ok(granted_credits)
+}
+
+/// Produces the new value of a JSON column from an optional full replacement
+/// and an optional merge patch; at most one of the two may be given. Changed
+/// documents are validated against the column's schema. Patches apply to the
+/// stored document upgraded to the current schema, so they can't knock out
+/// values an older version kept in a different shape.
+fn resolve_json_field<T: SchemaDocument>(
+    current: serde_json::Value,
+    replacement: Option<serde_json::Value>,
+    patch: Option<&serde_json::Value>,
+) -> Result<serde_json::Value, (StatusCode, String)> {
+    let value = match (replacement, patch) {
+        (Some(_), Some(_)) => {
+            let error_message = format!(
+                "'{name}' and '{name}_patch' cannot be used together",
+                name = T::NAME
+            );
+            error!("{}", error_message);
+            return Err((StatusCode::BAD_REQUEST, error_message));
+        }
+        (Some(replacement), None) => replacement,
+        (None, Some(patch)) => {
+            let mut value = serde_json::to_value(T::from_stored(current)).map_err(|e| {
+                let error_message = format!("serde to_value error: {}", e);
+                error!("{}", error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            })?;
+            utils::merge_patch::apply(&mut value, patch);
+            value
+        }
+        (None, None) => return Ok(current),
+    };
+    T::parse_for_write(value).map_err(|e| {
+        error!("{}", e);
+        (StatusCode::UNPROCESSABLE_ENTITY, e)
+    })
+}
+
+/// Builds the session body, adding the cached credit breakdown, the
+/// schedule of the free credit refill and the user's quota state. Quota state
+/// is left out rather than failing the request when the cache is unavailable.
+async fn session_response(
+    state: &ServiceState,
+    model: entity::session::Model,
+) -> Result<GetSessionResponse, (StatusCode, String)> {
+    let now = Utc::now();
+    let internal_error = |e: String| {
+        let error_message = format!(
+            "Failed to build session response for user ID {}: {}",
+            model.user_id, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+    let breakdown = utils::session::get_breakdown(state, &model)
+        .await
+        .map_err(internal_error)?;
+    let quotas = utils::quota::current(
+        state.cache.as_ref(),
+        &state.config.quota,
+        &breakdown.tier,
+        model.user_id,
+        now,
+    )
+    .await
+    .unwrap_or_else(|e| {
+        warn!(
+            "Leaving quota state out of session for user ID {}: {}",
+            model.user_id, e
+        );
+        Vec::new()
+    });
+    let next_refill_at = utils::refill::next_refill_at(&state.config.refill, &model, now);
+    Ok(GetSessionResponse::from(model)
+        .with_credit_buckets(breakdown.credit_buckets)
+        .with_next_refill_at(next_refill_at)
+        .with_quotas(quotas))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patch_keeps_v0_notifications_opt_out() {
+        let stored = json!({ "default_mode": "GPT-4o", "notifications": false });
+        let patch = json!({ "notifications": { "digest": "weekly" } });
+        let patched = resolve_json_field::<Preferences>(stored, None, Some(&patch)).unwrap();
+        assert_eq!(
+            patched,
+            json!({
+                "schema_version": 1,
+                "default_mode": "GPT-4o",
+                "notifications": { "enabled": false, "digest": "weekly" },
+            })
+        );
+    }
+
+    #[test]
+    fn patch_accepts_the_wire_flag() {
+        let stored = json!({ "schema_version": 1, "notifications": { "enabled": true } });
+        let patch = json!({ "notifications": false, "default_mode": null });
+        let patched = resolve_json_field::<Preferences>(stored, None, Some(&patch)).unwrap();
+        assert_eq!(patched["notifications"], json!({ "enabled": false }));
+        assert_eq!(
+            Preferences::from_stored(patched).to_wire(),
+            json!({ "notifications": false })
+        );
+    }
+}
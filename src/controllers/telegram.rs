@@ -0,0 +1,394 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::Set;
+use serde_json::json;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::{
+    dto::telegram::{PreCheckoutQuery, RefundedPayment, SuccessfulPayment, Update, WebhookReply},
+    entity::{
+        credit_bucket::CreditSource,
+        credit_ledger::LedgerEntryKind,
+        payment::{self, PaymentStatus},
+        plan::{self, BillingPeriod},
+        subscription::SubscriptionRenewal,
+    },
+    repositories,
+    utils::{
+        self,
+        session::{MissingSessionKey, SessionKey},
+        transaction::UnitOfWork,
+    },
+    ServiceState,
+};
+
+pub async fn webhook(
+    State(state): State<Arc<ServiceState>>,
+    Json(update): Json<Update>,
+) -> Result<Response, (StatusCode, String)> {
+    info!("Received Telegram update {}", update.update_id);
+
+    if let Some(query) = update.pre_checkout_query {
+        let reply = answer_pre_checkout_query(&state, query).await?;
+        return Ok(Json(reply).into_response());
+    }
+
+    if let Some(message) = update.message {
+        if let (Some(from), Some(successful_payment)) = (message.from, message.successful_payment) {
+            record_payment(state.clone(), from.id, successful_payment).await?;
+        }
+        if let Some(refunded_payment) = message.refunded_payment {
+            record_refund(state, refunded_payment).await?;
+        }
+    }
+
+    Ok(StatusCode::OK.into_response())
+}
+
+/// Approves the checkout only if the invoice still matches a purchasable plan,
+/// so a stale or tampered invoice is declined before the user is charged.
+async fn answer_pre_checkout_query(
+    state: &ServiceState,
+    query: PreCheckoutQuery,
+) -> Result<WebhookReply, (StatusCode, String)> {
+    let plan =
+        repositories::plan::find_active_by_code(state.db_read.as_ref(), &query.invoice_payload)
+            .await
+            .map_err(|e| {
+                let error_message = format!(
+                    "Failed to look up plan for pre-checkout query {}: {}",
+                    query.id, e
+                );
+                error!("{}", error_message);
+                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+            })?;
+
+    let error_message = check_invoice(plan.as_ref(), &query.currency, query.total_amount).err();
+    match &error_message {
+        Some(reason) => warn!(
+            "Declining pre-checkout query {} from user ID {}: {}",
+            query.id, query.from.id, reason
+        ),
+        None => info!(
+            "Approving pre-checkout query {} from user ID {}",
+            query.id, query.from.id
+        ),
+    }
+
+    Ok(WebhookReply::AnswerPreCheckoutQuery {
+        pre_checkout_query_id: query.id,
+        ok: error_message.is_none(),
+        error_message,
+    })
+}
+
+fn check_invoice(
+    plan: Option<&plan::Model>,
+    currency: &str,
+    total_amount: i64,
+) -> Result<(), String> {
+    let plan = plan.ok_or_else(|| "This product is no longer available".to_string())?;
+    if plan.currency != currency || plan.price != total_amount {
+        return Err("The price of this product has changed, please try again".to_string());
+    }
+    Ok(())
+}
+
+/// Applies a completed payment: records it, starts or extends the plan's
+/// subscription and grants its credits, all in one transaction. Telegram
+/// redelivers updates it didn't get a 2xx for, so a charge that was already
+/// recorded is acknowledged without being applied again.
+async fn record_payment(
+    state: Arc<ServiceState>,
+    user_id: i64,
+    successful_payment: SuccessfulPayment,
+) -> Result<(), (StatusCode, String)> {
+    let charge_id = successful_payment.telegram_payment_charge_id.clone();
+    info!("Received payment {} from user ID {}", charge_id, user_id);
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to record payment {}: {}", charge_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    if repositories::payment::find_by_telegram_charge_id(&*transaction, &charge_id)
+        .await
+        .map_err(internal_error)?
+        .is_some()
+    {
+        info!("Payment {} was already recorded", charge_id);
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    if !repositories::user::exist_by_user_id(&transaction, user_id)
+        .await
+        .map_err(internal_error)?
+    {
+        utils::signup::create_account(&transaction, &state.config, user_id, None, None)
+            .await
+            .map_err(internal_error)?;
+        let cache = state.cache.clone();
+        transaction.after_commit("clear_missing_session_marker", async move {
+            utils::session::del(cache.as_ref(), &MissingSessionKey { user_id })
+                .await
+                .map(|_| ())
+        });
+    }
+
+    // Telegram has already taken the money, so a payment we can't match is
+    // recorded without applying it and acknowledged rather than retried
+    // forever, leaving it for support to refund.
+    let plan = repositories::plan::find_by_code(&*transaction, &successful_payment.invoice_payload)
+        .await
+        .map_err(internal_error)?;
+    if let Err(reason) = check_invoice(
+        plan.as_ref(),
+        &successful_payment.currency,
+        successful_payment.total_amount,
+    ) {
+        metrics::counter!("payments_unmatched_total").increment(1);
+        error!(
+            "Payment {} from user ID {} does not match a plan ({}): payload '{}', {} {}",
+            charge_id,
+            user_id,
+            reason,
+            successful_payment.invoice_payload,
+            successful_payment.total_amount,
+            successful_payment.currency
+        );
+        repositories::payment::save(
+            &transaction,
+            payment::ActiveModel {
+                id: Set(Uuid::new_v4()),
+                user_id: Set(user_id),
+                plan_id: Set(plan.map(|plan| plan.id)),
+                telegram_payment_charge_id: Set(charge_id.clone()),
+                provider_payment_charge_id: Set(successful_payment.provider_payment_charge_id),
+                currency: Set(successful_payment.currency),
+                total_amount: Set(successful_payment.total_amount),
+                invoice_payload: Set(successful_payment.invoice_payload),
+                status: Set(PaymentStatus::Unmatched),
+                subscription_id: Set(None),
+                credits_granted: Set(0),
+                created_at: Set(now),
+                updated_at: Set(now),
+            },
+        )
+        .await
+        .map_err(internal_error)?;
+        transaction
+            .commit()
+            .await
+            .map_err(|e| internal_error(e.to_string()))?;
+        return Ok(());
+    }
+    let plan = plan.unwrap();
+
+    let subscription = if plan.period == BillingPeriod::OneTime {
+        None
+    } else {
+        let period_end = successful_payment
+            .subscription_expiration_date
+            .and_then(|timestamp| DateTime::<Utc>::from_timestamp(timestamp, 0));
+        let subscription = utils::billing::start_subscription(
+            &transaction,
+            user_id,
+            &plan,
+            period_end,
+            SubscriptionRenewal::Payment,
+            now,
+        )
+        .await
+        .map_err(internal_error)?;
+        Some(subscription)
+    };
+    let subscription_id = subscription.as_ref().map(|subscription| subscription.id);
+    let (source, expires_at) = match &subscription {
+        Some(subscription) => (
+            CreditSource::Subscription,
+            Some(subscription.current_period_end),
+        ),
+        None => (
+            CreditSource::Purchase,
+            state
+                .config
+                .billing
+                .credit_expiry(CreditSource::Purchase, now),
+        ),
+    };
+
+    repositories::credit_ledger::record(
+        &transaction,
+        user_id,
+        plan.credits_per_period,
+        LedgerEntryKind::Payment,
+        format!("payment:{}", charge_id),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    repositories::payment::save(
+        &transaction,
+        payment::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            user_id: Set(user_id),
+            plan_id: Set(Some(plan.id)),
+            telegram_payment_charge_id: Set(charge_id.clone()),
+            provider_payment_charge_id: Set(successful_payment.provider_payment_charge_id),
+            currency: Set(successful_payment.currency),
+            total_amount: Set(successful_payment.total_amount),
+            invoice_payload: Set(successful_payment.invoice_payload),
+            status: Set(PaymentStatus::Paid),
+            subscription_id: Set(subscription_id),
+            credits_granted: Set(plan.credits_per_period),
+            created_at: Set(now),
+            updated_at: Set(now),
+        },
+    )
+    .await
+    .map_err(internal_error)?;
+
+    let mut sessions: Vec<_> = utils::credits::grant(
+        &transaction,
+        user_id,
+        plan.credits_per_period,
+        source,
+        expires_at,
+        now,
+    )
+    .await
+    .map_err(internal_error)?
+    .into_iter()
+    .collect();
+    sessions.extend(
+        utils::referral::reward_after_purchase(
+            &transaction,
+            &state.config.referral,
+            &state.config.billing,
+            user_id,
+            now,
+        )
+        .await
+        .map_err(internal_error)?,
+    );
+    for session in sessions {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey {
+                    user_id: session.user_id,
+                },
+                session,
+            ),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    metrics::counter!("payments_total", "plan" => plan.code.clone()).increment(1);
+    info!(
+        "Payment {} applied: user ID {} bought plan '{}'",
+        charge_id, user_id, plan.code
+    );
+    Ok(())
+}
+
+/// Reverses a payment Telegram refunded on its own, e.g. after a dispute.
+async fn record_refund(
+    state: Arc<ServiceState>,
+    refunded_payment: RefundedPayment,
+) -> Result<(), (StatusCode, String)> {
+    let charge_id = refunded_payment.telegram_payment_charge_id;
+    info!(
+        "Received refund of payment {}: {} {}",
+        charge_id, refunded_payment.total_amount, refunded_payment.currency
+    );
+    let internal_error = |e: String| {
+        let error_message = format!("Failed to record refund of payment {}: {}", charge_id, e);
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let Some(payment) =
+        repositories::payment::find_by_telegram_charge_id(&*transaction, &charge_id)
+            .await
+            .map_err(internal_error)?
+    else {
+        warn!("Refunded payment {} was never recorded", charge_id);
+        return Ok(());
+    };
+
+    let now = Utc::now();
+    let policy = state.config.billing.refund_policy;
+    let Some(reversal) = utils::billing::reverse_payment(&transaction, &payment, policy, now)
+        .await
+        .map_err(internal_error)?
+    else {
+        info!("Payment {} was already refunded", charge_id);
+        return Ok(());
+    };
+
+    repositories::audit_log::record(
+        &transaction,
+        "telegram",
+        "payment.refund",
+        Some(payment.user_id),
+        json!({
+            "telegram_payment_charge_id": charge_id,
+            "policy": format!("{:?}", policy),
+            "credits_granted": payment.credits_granted,
+            "credits_reversed": reversal.credits_reversed,
+            "subscription_canceled": reversal.subscription_canceled,
+        }),
+        now,
+    )
+    .await
+    .map_err(internal_error)?;
+
+    if let Some(session) = reversal.session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey {
+                    user_id: payment.user_id,
+                },
+                session,
+            ),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    metrics::counter!("payments_refunded_total", "source" => "telegram").increment(1);
+    info!(
+        "Payment {} refunded by Telegram: {} credits reversed",
+        charge_id, reversal.credits_reversed
+    );
+    Ok(())
+}
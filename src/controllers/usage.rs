@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde_json::json;
+use tracing::{error, info};
+
+use crate::{
+    dto::{request::RecordUsageRequest, response::RecordUsageResponse},
+    entity::credit_ledger::{self, LedgerEntryKind},
+    repositories,
+    utils::{
+        self,
+        pricing::{ChargeLine, Usage},
+        session::SessionKey,
+        transaction::UnitOfWork,
+    },
+    ServiceState,
+};
+
+/// Charges a user for one model call, priced from the catalogue in effect now.
+///
+/// The charge is keyed by `request_id` in the ledger, so a worker can retry a
+/// call it didn't get an answer for; a replay reports the original charge.
+pub async fn record_usage(
+    State(state): State<Arc<ServiceState>>,
+    Json(req): Json<RecordUsageRequest>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    info!(
+        "Received 'record_usage' request {} for user ID {} on model {}",
+        req.request_id, req.user_id, req.model
+    );
+
+    validate_usage(&req).map_err(|error_message| {
+        error!("{}", error_message);
+        (StatusCode::UNPROCESSABLE_ENTITY, error_message)
+    })?;
+
+    let internal_error = |e: String| {
+        let error_message = format!(
+            "Failed to record usage {} for user ID {}: {}",
+            req.request_id, req.user_id, e
+        );
+        error!("{}", error_message);
+        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+    };
+
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    let session_data = repositories::session::find_by_user_id(&*transaction, req.user_id)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| {
+            let error_message = format!("Session record not found for user ID: {}", req.user_id);
+            error!("{}", error_message);
+            (StatusCode::NOT_FOUND, error_message)
+        })?;
+
+    let idempotency_key = format!("usage:{}", req.request_id);
+    if let Some(entry) =
+        repositories::credit_ledger::find_by_idempotency_key(&*transaction, &idempotency_key)
+            .await
+            .map_err(internal_error)?
+    {
+        return replay(req, entry, session_data.credits_remaining);
+    }
+
+    let now = Utc::now();
+    let prices = repositories::model_price::find_effective_by_model(&*transaction, &req.model, now)
+        .await
+        .map_err(internal_error)?;
+    let usage = Usage {
+        requests: req.requests,
+        input_tokens: req.input_tokens,
+        output_tokens: req.output_tokens,
+        images: req.images,
+    };
+    let charge = utils::pricing::quote(&req.model, &prices, usage).map_err(|error_message| {
+        error!("{}", error_message);
+        (StatusCode::UNPROCESSABLE_ENTITY, error_message)
+    })?;
+
+    if !repositories::credit_ledger::record_with_details(
+        &transaction,
+        req.user_id,
+        -charge.credits,
+        LedgerEntryKind::Usage,
+        idempotency_key.clone(),
+        Some(json!({
+            "request_id": req.request_id,
+            "model": req.model,
+            "usage": usage,
+            "lines": charge.lines,
+        })),
+        now,
+    )
+    .await
+    .map_err(internal_error)?
+    {
+        // A concurrent call with the same request_id committed its charge
+        // while we waited on the ledger insert; answer with that one.
+        drop(transaction);
+        let entry = repositories::credit_ledger::find_by_idempotency_key(
+            state.db.as_ref(),
+            &idempotency_key,
+        )
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| internal_error("conflicting charge not found".to_string()))?;
+        let session = repositories::session::find_by_user_id(state.db.as_ref(), req.user_id)
+            .await
+            .map_err(internal_error)?
+            .ok_or_else(|| internal_error("session disappeared while charging".to_string()))?;
+        return replay(req, entry, session.credits_remaining);
+    }
+
+    let session = utils::credits::debit(&transaction, req.user_id, charge.credits, now)
+        .await
+        .map_err(internal_error)?
+        .ok_or_else(|| internal_error("session disappeared while charging".to_string()))?;
+    // The balance is checked after the debit, against the locked row, so that
+    // concurrent calls can't both pass a check made before either of them.
+    if session.credits_remaining < 0 {
+        let error_message = format!(
+            "User ID {} has {} credits, {} needed",
+            req.user_id,
+            session.credits_remaining + charge.credits,
+            charge.credits
+        );
+        error!("{}", error_message);
+        return Err((StatusCode::PAYMENT_REQUIRED, error_message));
+    }
+
+    transaction.after_commit(
+        "refresh_session_cache",
+        utils::session::write_after_commit(
+            state.clone(),
+            SessionKey {
+                user_id: req.user_id,
+            },
+            session.clone(),
+        ),
+    );
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| internal_error(e.to_string()))?;
+
+    metrics::counter!("usage_credits_charged_total", "model" => req.model.clone())
+        .increment(charge.credits as u64);
+    info!(
+        "Charged user ID {} {} credits for usage {}",
+        req.user_id, charge.credits, req.request_id
+    );
+
+    let response = Json(RecordUsageResponse {
+        request_id: req.request_id,
+        credits_charged: charge.credits,
+        lines: charge.lines,
+        credits_remaining: session.credits_remaining,
+        replayed: false,
+    })
+    .into_response();
+    Ok(response)
+}
+
+/// Answers a retried call with the charge already recorded for it.
+fn replay(
+    req: RecordUsageRequest,
+    entry: credit_ledger::Model,
+    credits_remaining: i64,
+) -> Result<Response, (StatusCode, String)> {
+    if entry.user_id != req.user_id {
+        let error_message = format!(
+            "Usage {} was already charged to another user",
+            req.request_id
+        );
+        error!("{}", error_message);
+        return Err((StatusCode::CONFLICT, error_message));
+    }
+    let lines = entry
+        .details
+        .and_then(|details| details.get("lines").cloned())
+        .and_then(|lines| serde_json::from_value::<Vec<ChargeLine>>(lines).ok())
+        .unwrap_or_default();
+    let response = Json(RecordUsageResponse {
+        request_id: req.request_id,
+        credits_charged: -entry.amount,
+        lines,
+        credits_remaining,
+        replayed: true,
+    })
+    .into_response();
+    Ok(response)
+}
+
+fn validate_usage(req: &RecordUsageRequest) -> Result<(), String> {
+    if req.request_id.is_empty() || req.request_id.len() > 128 {
+        return Err("'request_id' must be 1-128 characters".to_string());
+    }
+    if req.model.is_empty() {
+        return Err("'model' cannot be empty".to_string());
+    }
+    if req.requests < 0 || req.input_tokens < 0 || req.output_tokens < 0 || req.images < 0 {
+        return Err("Usage counts cannot be negative".to_string());
+    }
+    Ok(())
+}
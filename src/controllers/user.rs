@@ -5,13 +5,19 @@ use axum_extra::{
     headers::{authorization::Bearer, Authorization},
     TypedHeader,
 };
+use chrono::Utc;
 use sea_orm::TransactionTrait;
 use tracing::{error, info};
 
 use crate::{
     dto::{request::RefreshRequest, response::UserResponse},
     repositories::{session, user},
-    utils::{initdata, jwt, jwt::UserClaims},
+    utils::{
+        self, account, initdata, jwt,
+        jwt::UserClaims,
+        session::{MissingSessionKey, SessionKey},
+        transaction::UnitOfWork,
+    },
     ServiceState,
 };
 
@@ -33,8 +39,9 @@ pub async fn login(
     }
 
     info!("Received login request for user ID {}", user_id);
+    let username = initdata::get_username(creds.token());
 
-    let transaction = state.db.begin().await.map_err(|e| {
+    let mut transaction = UnitOfWork::begin(&state.db).await.map_err(|e| {
         let error_message = format!("Database transaction initiation failed: {}", e);
         error!("{}", error_message);
         (StatusCode::INTERNAL_SERVER_ERROR, error_message)
@@ -43,15 +50,33 @@ pub async fn login(
     let mut session_table_id: uuid::Uuid = uuid::Uuid::new_v4();
     match user::exist_by_user_id(&transaction, user_id).await {
         Ok(true) => {
-            let user_info = user::find_by_user_id(&transaction, user_id)
+            let user_info = user::find_by_user_id(&*transaction, user_id)
                 .await
                 .map_err(|e| {
                     let error_message = format!("Failed to retrieve user information: {}", e);
                     error!("{}", error_message);
                     (StatusCode::INTERNAL_SERVER_ERROR, error_message)
                 })?;
+            if let Some(reason) = user_info.as_ref().and_then(|user_info| {
+                account::blocked_reason(user_info.status, user_info.suspended_until, Utc::now())
+            }) {
+                error!("Login refused for user ID {}: {}", user_id, reason);
+                return Err((StatusCode::FORBIDDEN, reason));
+            }
+            if user_info
+                .as_ref()
+                .is_some_and(|user_info| user_info.username != username)
+            {
+                user::set_username(&transaction, user_id, username.clone(), Utc::now())
+                    .await
+                    .map_err(|e| {
+                        let error_message = format!("Failed to update username: {}", e);
+                        error!("{}", error_message);
+                        (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+                    })?;
+            }
             let session_info = match user_info {
-                Some(_) => session::find_by_user_id(&transaction, user_id)
+                Some(_) => session::find_by_user_id(&*transaction, user_id)
                     .await
                     .map_err(|e| {
                         let error_message =
@@ -78,17 +103,56 @@ pub async fn login(
         }
 
         Ok(false) => {
-            user::save(&transaction, user_id).await.map_err(|e| {
-                let error_message = format!("User save operation failed: {}", e);
+            let start_param = initdata::get_start_param(creds.token());
+            session_table_id = utils::signup::create_account(
+                &transaction,
+                &state.config,
+                user_id,
+                username.clone(),
+                start_param.as_deref(),
+            )
+            .await
+            .map_err(|e| {
+                let error_message = format!("Account creation failed: {}", e);
                 error!("{}", error_message);
                 (StatusCode::INTERNAL_SERVER_ERROR, error_message)
             })?;
 
-            session_table_id = session::save(&transaction, user_id).await.map_err(|e| {
-                let error_message = format!("Session save operation failed: {}", e);
-                error!("{}", error_message);
-                (StatusCode::INTERNAL_SERVER_ERROR, error_message)
-            })?;
+            let cache = state.cache.clone();
+            transaction.after_commit("clear_missing_session_marker", async move {
+                utils::session::del(cache.as_ref(), &MissingSessionKey { user_id })
+                    .await
+                    .map(|_| ())
+            });
+
+            if let Some(code) = start_param {
+                let sessions = utils::referral::record_signup(
+                    &transaction,
+                    &state.config.referral,
+                    &state.config.billing,
+                    user_id,
+                    &code,
+                    Utc::now(),
+                )
+                .await
+                .map_err(|e| {
+                    let error_message = format!("Referral recording failed: {}", e);
+                    error!("{}", error_message);
+                    (StatusCode::INTERNAL_SERVER_ERROR, error_message)
+                })?;
+                for session in sessions {
+                    transaction.after_commit(
+                        "refresh_session_cache",
+                        utils::session::write_after_commit(
+                            state.clone(),
+                            SessionKey {
+                                user_id: session.user_id,
+                            },
+                            session,
+                        ),
+                    );
+                }
+            }
         }
 
         Err(e) => {
@@ -178,6 +242,25 @@ pub async fn refresh(
         return Err((StatusCode::NOT_FOUND, error_message));
     }
 
+    if let Some(reason) = user_info.as_ref().and_then(|user_info| {
+        account::blocked_reason(user_info.status, user_info.suspended_until, Utc::now())
+    }) {
+        error!("Refresh refused for user ID {}: {}", user_id, reason);
+        return Err((StatusCode::FORBIDDEN, reason));
+    }
+    if session_info.as_ref().is_some_and(|session_info| {
+        account::is_revoked(
+            session_info.tokens_revoked_at,
+            user_claims.claims.issued_at_ms(),
+        )
+    }) {
+        error!("Refresh refused for user ID {}: token was revoked", user_id);
+        return Err((
+            StatusCode::UNAUTHORIZED,
+            "Refresh token has been revoked".to_string(),
+        ));
+    }
+
     if user_claims.claims.uid != user_info.clone().unwrap().user_id
         || user_claims.claims.sid != session_info.clone().unwrap().id
     {
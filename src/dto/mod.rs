@@ -1,2 +1,4 @@
 pub mod request;
 pub mod response;
+pub mod session_data;
+pub mod telegram;
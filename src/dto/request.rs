@@ -1,4 +1,8 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+
+use crate::entity::model_price::PriceUnit;
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct RefreshRequest {
     pub refresh_token: String,
@@ -7,8 +11,123 @@ pub struct RefreshRequest {
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct SetSessionRequest {
     pub user_id: i64,
+    /// `false` ends the user's subscription immediately. `true` without a
+    /// `plan_code` only applies to users without subscription rows, whose flag
+    /// is stored directly, and grants the flat subscription credits.
     pub subscription_status: Option<bool>,
+    /// Starts a subscription on this plan, replacing any current one, and
+    /// grants the plan's credits for the first period.
+    pub plan_code: Option<String>,
+    pub cancel_at_period_end: Option<bool>,
     pub credits_remaining: Option<i64>,
     pub preferences: Option<serde_json::Value>,
     pub session_metadata: Option<serde_json::Value>,
+    /// RFC 7396 merge patch applied to the stored preferences. Mutually
+    /// exclusive with `preferences`.
+    pub preferences_patch: Option<serde_json::Value>,
+    /// RFC 7396 merge patch applied to the stored session metadata. Mutually
+    /// exclusive with `session_metadata`.
+    pub session_metadata_patch: Option<serde_json::Value>,
+    /// Rejects the update with 409 unless the session is still at this version.
+    pub expected_version: Option<i64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RefundPaymentRequest {
+    /// Why support is refunding, kept in the audit log.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct BanUserRequest {
+    /// Why support is blocking the user, kept on the user and in the audit log.
+    pub reason: String,
+    /// Suspends the user until this time instead of banning them outright.
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UnbanUserRequest {
+    pub reason: Option<String>,
+}
+
+/// Exactly one of the two is expected.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SearchUsersQuery {
+    pub user_id: Option<i64>,
+    /// Matched case-insensitively as a prefix; a leading `@` is ignored.
+    pub username: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct AdjustCreditsRequest {
+    /// Positive to add credits, negative to take them away.
+    pub amount: i64,
+    /// Why support is correcting the balance, kept in the ledger and audit log.
+    pub reason: String,
+    /// Retrying with the same key never applies the adjustment twice.
+    pub idempotency_key: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RedeemPromoRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CreatePromoCodeRequest {
+    pub code: String,
+    #[serde(default)]
+    pub credits: i64,
+    /// Plan to start a trial subscription on.
+    pub plan_code: Option<String>,
+    pub trial_days: Option<i32>,
+    pub max_redemptions: Option<i64>,
+    pub per_user_limit: Option<i32>,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+}
+
+fn default_requests() -> i64 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RecordUsageRequest {
+    pub user_id: i64,
+    /// Unique per model call; retrying with the same ID never charges twice.
+    pub request_id: String,
+    pub model: String,
+    #[serde(default = "default_requests")]
+    pub requests: i64,
+    #[serde(default)]
+    pub input_tokens: i64,
+    #[serde(default)]
+    pub output_tokens: i64,
+    #[serde(default)]
+    pub images: i64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct CreateModelPriceRequest {
+    pub model: String,
+    pub unit: PriceUnit,
+    pub credits: i64,
+    /// Defaults to 1; e.g. 1000 to price tokens per thousand.
+    pub per_units: Option<i64>,
+    /// Defaults to now; a future date schedules a price change.
+    pub effective_from: Option<DateTime<Utc>>,
+}
+
+fn default_quota_cost() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ConsumeQuotaRequest {
+    pub user_id: i64,
+    /// Name of a quota configured in `QUOTAS`.
+    pub quota: String,
+    #[serde(default = "default_quota_cost")]
+    pub cost: u64,
 }
@@ -1,4 +1,19 @@
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use uuid::Uuid;
+
+use crate::{
+    dto::session_data::{Preferences, SchemaDocument, SessionMetadata},
+    entity::{
+        credit_bucket::{self, CreditSource},
+        credit_ledger,
+        model_price::{self, PriceUnit},
+        promo_code, session, subscription,
+        user::{self, UserStatus},
+    },
+    utils::{pricing::ChargeLine, quota::QuotaState},
+};
+
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct UserResponse {
     pub access_token: String,
@@ -8,7 +23,224 @@ pub struct UserResponse {
 #[derive(Debug, Clone, Default, Serialize)]
 pub struct GetSessionResponse {
     pub subscription_status: bool,
+    /// The whole balance, including any part not held in `credit_buckets`
+    /// (credits from before buckets existed, or debt).
     pub credits_remaining: i64,
+    /// Spendable credits by source, in the order they are used up.
+    pub credit_buckets: Vec<CreditBucketResponse>,
     pub preferences: serde_json::Value,
     pub session_metadata: serde_json::Value,
+    pub version: i64,
+    /// When free credits are next topped up, if the user qualifies.
+    pub next_refill_at: Option<DateTime<Utc>>,
+    pub quotas: Vec<QuotaState>,
+}
+
+impl GetSessionResponse {
+    pub fn with_credit_buckets(mut self, buckets: Vec<credit_bucket::Model>) -> Self {
+        self.credit_buckets = buckets.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_quotas(mut self, quotas: Vec<QuotaState>) -> Self {
+        self.quotas = quotas;
+        self
+    }
+
+    pub fn with_next_refill_at(mut self, next_refill_at: Option<DateTime<Utc>>) -> Self {
+        self.next_refill_at = next_refill_at;
+        self
+    }
+}
+
+impl From<session::Model> for GetSessionResponse {
+    fn from(model: session::Model) -> Self {
+        Self {
+            subscription_status: model.subscription_status,
+            credits_remaining: model.credits_remaining,
+            credit_buckets: Vec::new(),
+            preferences: Preferences::from_stored(model.preferences).to_wire(),
+            session_metadata: SessionMetadata::from_stored(model.session_metadata).to_wire(),
+            version: model.version,
+            next_refill_at: None,
+            quotas: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CreditBucketResponse {
+    pub source: CreditSource,
+    pub remaining: i64,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<credit_bucket::Model> for CreditBucketResponse {
+    fn from(model: credit_bucket::Model) -> Self {
+        Self {
+            source: model.source,
+            remaining: model.remaining,
+            expires_at: model.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RefundPaymentResponse {
+    pub telegram_payment_charge_id: String,
+    pub user_id: i64,
+    pub credits_reversed: i64,
+    pub subscription_canceled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UserStatusResponse {
+    pub user_id: i64,
+    pub status: UserStatus,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub status_reason: Option<String>,
+}
+
+impl From<user::Model> for UserStatusResponse {
+    fn from(model: user::Model) -> Self {
+        Self {
+            user_id: model.user_id,
+            status: model.status,
+            suspended_until: model.suspended_until,
+            status_reason: model.status_reason,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminUserSummary {
+    pub user_id: i64,
+    pub username: Option<String>,
+    pub credits_remaining: i64,
+    pub total_credits: i64,
+    pub subscription_status: bool,
+    pub status: UserStatus,
+    pub suspended_until: Option<DateTime<Utc>>,
+    pub status_reason: Option<String>,
+    pub version: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<user::Model> for AdminUserSummary {
+    fn from(model: user::Model) -> Self {
+        Self {
+            user_id: model.user_id,
+            username: model.username,
+            credits_remaining: model.credits_remaining,
+            total_credits: model.total_credits,
+            subscription_status: model.subscription_status,
+            status: model.status,
+            suspended_until: model.suspended_until,
+            status_reason: model.status_reason,
+            version: model.version,
+            created_at: model.created_at,
+            updated_at: model.updated_at,
+        }
+    }
+}
+
+/// Everything support needs to look into a user's account.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdminUserResponse {
+    pub user: AdminUserSummary,
+    pub session: Option<session::Model>,
+    pub subscription: Option<subscription::Model>,
+    pub credit_buckets: Vec<CreditBucketResponse>,
+    /// Newest first.
+    pub recent_ledger: Vec<credit_ledger::Model>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AdjustCreditsResponse {
+    pub user_id: i64,
+    pub amount: i64,
+    pub credits_remaining: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RedeemPromoResponse {
+    pub code: String,
+    pub credits_granted: i64,
+    pub plan_code: Option<String>,
+    pub subscription_period_end: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromoCodeResponse {
+    pub code: String,
+    pub credits: i64,
+    pub plan_id: Option<Uuid>,
+    pub trial_days: Option<i32>,
+    pub max_redemptions: Option<i64>,
+    pub per_user_limit: i32,
+    pub redemption_count: i64,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub is_active: bool,
+}
+
+impl From<promo_code::Model> for PromoCodeResponse {
+    fn from(model: promo_code::Model) -> Self {
+        Self {
+            code: model.code,
+            credits: model.credits,
+            plan_id: model.plan_id,
+            trial_days: model.trial_days,
+            max_redemptions: model.max_redemptions,
+            per_user_limit: model.per_user_limit,
+            redemption_count: model.redemption_count,
+            valid_from: model.valid_from,
+            valid_until: model.valid_until,
+            is_active: model.is_active,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReferralsResponse {
+    pub referral_code: String,
+    pub invite_link: String,
+    pub invite_count: u64,
+    pub credits_earned: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RecordUsageResponse {
+    pub request_id: String,
+    pub credits_charged: i64,
+    pub lines: Vec<ChargeLine>,
+    pub credits_remaining: i64,
+    /// `true` when the request ID was already charged and nothing changed.
+    pub replayed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelPriceResponse {
+    pub id: Uuid,
+    pub model: String,
+    pub unit: PriceUnit,
+    pub credits: i64,
+    pub per_units: i64,
+    pub effective_from: DateTime<Utc>,
+    pub created_by: String,
+}
+
+impl From<model_price::Model> for ModelPriceResponse {
+    fn from(model: model_price::Model) -> Self {
+        Self {
+            id: model.id,
+            model: model.model,
+            unit: model.unit,
+            credits: model.credits,
+            per_units: model.per_units,
+            effective_from: model.effective_from,
+            created_by: model.created_by,
+        }
+    }
 }
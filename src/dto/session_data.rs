@@ -0,0 +1,265 @@
+use garde::Validate;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// Oldest entries are dropped once `recent_actions` grows past this.
+pub const RECENT_ACTIONS_LIMIT: usize = 20;
+/// Upper bound on the serialized size of a submitted document.
+const MAX_DOCUMENT_BYTES: usize = 16 * 1024;
+
+/// A JSON column with a typed, versioned schema.
+///
+/// Documents carry a `schema_version`; rows written under an older version are
+/// upgraded one step at a time when read, and keys the schema doesn't know
+/// about are kept in the type's `extra` map rather than dropped.
+pub trait SchemaDocument: Default + Serialize + DeserializeOwned + Validate<Context = ()> {
+    const NAME: &'static str;
+    const SCHEMA_VERSION: u32;
+
+    /// Rewrites `document` from schema `from_version` to `from_version + 1`.
+    fn migrate(_from_version: u32, _document: &mut Map<String, Value>) {}
+
+    /// Brings a parsed document within limits that are enforced by trimming
+    /// rather than rejection.
+    fn normalize(&mut self) {}
+
+    /// Parses a stored document. A row that no longer parses is logged and
+    /// read as the defaults, so one bad blob can't break session reads.
+    fn from_stored(value: Value) -> Self {
+        match upgrade::<Self>(value)
+            .and_then(|value| serde_json::from_value(value).map_err(|e| format!("{}", e)))
+        {
+            Ok(document) => document,
+            Err(e) => {
+                warn!("Ignoring unreadable stored {}: {}", Self::NAME, e);
+                Self::default()
+            }
+        }
+    }
+
+    /// The document as clients see it: the shape the column had before it was
+    /// versioned, so `schema_version` stays internal.
+    fn to_wire(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Value::Object(document) = &mut value {
+            document.remove("schema_version");
+        }
+        value
+    }
+
+    /// Validates a document submitted by a client and returns the form it
+    /// should be stored in.
+    fn parse_for_write(value: Value) -> Result<Value, String> {
+        if value.to_string().len() > MAX_DOCUMENT_BYTES {
+            return Err(format!(
+                "{} exceeds {} bytes",
+                Self::NAME,
+                MAX_DOCUMENT_BYTES
+            ));
+        }
+        let mut document: Self = serde_json::from_value(upgrade::<Self>(value)?)
+            .map_err(|e| format!("Invalid {}: {}", Self::NAME, e))?;
+        document.normalize();
+        document
+            .validate()
+            .map_err(|e| format!("Invalid {}: {}", Self::NAME, e))?;
+        serde_json::to_value(document).map_err(|e| format!("serde to_value error: {}", e))
+    }
+}
+
+fn upgrade<T: SchemaDocument>(value: Value) -> Result<Value, String> {
+    let mut document = match value {
+        Value::Object(document) => document,
+        // Rows created before these columns had a schema default to `{}`.
+        Value::Null => Map::new(),
+        _ => return Err(format!("{} must be a JSON object", T::NAME)),
+    };
+    let mut version = document
+        .get("schema_version")
+        .and_then(Value::as_u64)
+        .unwrap_or(0) as u32;
+    while version < T::SCHEMA_VERSION {
+        T::migrate(version, &mut document);
+        version += 1;
+    }
+    document.insert("schema_version".to_string(), version.into());
+    Ok(Value::Object(document))
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct Preferences {
+    #[garde(skip)]
+    pub schema_version: u32,
+    #[garde(length(min = 1, max = 64))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_mode: Option<String>,
+    #[garde(dive)]
+    #[serde(deserialize_with = "flag_or_settings")]
+    pub notifications: Notifications,
+    #[serde(flatten)]
+    #[garde(skip)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            schema_version: Self::SCHEMA_VERSION,
+            default_mode: None,
+            notifications: Notifications::default(),
+            extra: Map::new(),
+        }
+    }
+}
+
+impl SchemaDocument for Preferences {
+    const NAME: &'static str = "preferences";
+    const SCHEMA_VERSION: u32 = 1;
+
+    /// Clients still read and write `notifications` as the v0 on/off flag.
+    fn to_wire(&self) -> Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Value::Object(document) = &mut value {
+            document.remove("schema_version");
+            document.insert(
+                "notifications".to_string(),
+                self.notifications.enabled.into(),
+            );
+        }
+        value
+    }
+
+    fn migrate(from_version: u32, document: &mut Map<String, Value>) {
+        // v0 stored `notifications` as a bare on/off flag.
+        if from_version == 0 {
+            if let Some(enabled) = document.get("notifications").and_then(Value::as_bool) {
+                document.insert(
+                    "notifications".to_string(),
+                    serde_json::json!({ "enabled": enabled }),
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct Notifications {
+    #[garde(skip)]
+    pub enabled: bool,
+    #[serde(flatten)]
+    #[garde(skip)]
+    pub extra: Map<String, Value>,
+}
+
+/// Reads `notifications` either as full settings or as the bare on/off flag
+/// clients send.
+fn flag_or_settings<'de, D>(deserializer: D) -> Result<Notifications, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Flag(bool),
+        Settings(Notifications),
+    }
+
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Flag(enabled) => Notifications {
+            enabled,
+            ..Notifications::default()
+        },
+        Stored::Settings(notifications) => notifications,
+    })
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            extra: Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+pub struct SessionMetadata {
+    #[garde(skip)]
+    pub schema_version: u32,
+    #[garde(length(min = 1, max = 64))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_mode_used: Option<String>,
+    /// Most recent last.
+    #[garde(length(max = RECENT_ACTIONS_LIMIT), inner(length(min = 1, max = 128)))]
+    pub recent_actions: Vec<String>,
+    #[serde(flatten)]
+    #[garde(skip)]
+    pub extra: Map<String, Value>,
+}
+
+impl Default for SessionMetadata {
+    fn default() -> Self {
+        Self {
+            schema_version: Self::SCHEMA_VERSION,
+            last_mode_used: None,
+            recent_actions: Vec::new(),
+            extra: Map::new(),
+        }
+    }
+}
+
+impl SchemaDocument for SessionMetadata {
+    const NAME: &'static str = "session_metadata";
+    const SCHEMA_VERSION: u32 = 1;
+
+    fn normalize(&mut self) {
+        let overflow = self
+            .recent_actions
+            .len()
+            .saturating_sub(RECENT_ACTIONS_LIMIT);
+        self.recent_actions.drain(..overflow);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn preferences_keep_the_v0_wire_shape() {
+        let preferences = Preferences::from_stored(json!({
+            "default_mode": "GPT-4o",
+            "notifications": false,
+            "theme": "dark",
+        }));
+        assert!(!preferences.notifications.enabled);
+        assert_eq!(
+            preferences.to_wire(),
+            json!({ "default_mode": "GPT-4o", "notifications": false, "theme": "dark" })
+        );
+    }
+
+    #[test]
+    fn preferences_accept_flag_or_settings() {
+        let stored = Preferences::parse_for_write(json!({
+            "schema_version": 1,
+            "notifications": false,
+        }))
+        .unwrap();
+        assert_eq!(stored["notifications"], json!({ "enabled": false }));
+
+        let stored = Preferences::parse_for_write(json!({
+            "notifications": { "enabled": false, "digest": "weekly" },
+        }))
+        .unwrap();
+        assert_eq!(
+            stored["notifications"],
+            json!({ "enabled": false, "digest": "weekly" })
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! The subset of Bot API types the payment webhook reads.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Update {
+    pub update_id: i64,
+    pub message: Option<Message>,
+    pub pre_checkout_query: Option<PreCheckoutQuery>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct User {
+    pub id: i64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Message {
+    pub from: Option<User>,
+    pub successful_payment: Option<SuccessfulPayment>,
+    pub refunded_payment: Option<RefundedPayment>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PreCheckoutQuery {
+    pub id: String,
+    pub from: User,
+    pub currency: String,
+    pub total_amount: i64,
+    pub invoice_payload: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SuccessfulPayment {
+    pub currency: String,
+    pub total_amount: i64,
+    pub invoice_payload: String,
+    /// Unix time at which a recurring Stars subscription will next renew.
+    pub subscription_expiration_date: Option<i64>,
+    pub telegram_payment_charge_id: String,
+    pub provider_payment_charge_id: String,
+}
+
+/// Sent when Telegram refunds a payment without us asking, e.g. after a
+/// dispute.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RefundedPayment {
+    pub currency: String,
+    pub total_amount: i64,
+    pub telegram_payment_charge_id: String,
+}
+
+/// A Bot API call returned as the webhook response body, which Telegram
+/// executes on our behalf.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "method")]
+pub enum WebhookReply {
+    #[serde(rename = "answerPreCheckoutQuery")]
+    AnswerPreCheckoutQuery {
+        pre_checkout_query_id: String,
+        ok: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_message: Option<String>,
+    },
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Who did what to whom, for actions taken outside a user's own session.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "audit_log")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    /// An admin ID, or the name of the system that acted, e.g. `telegram`.
+    pub actor: String,
+    /// Dotted action name, e.g. `payment.refund`.
+    pub action: String,
+    #[sea_orm(indexed)]
+    pub target_user_id: Option<i64>,
+    pub details: Json,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
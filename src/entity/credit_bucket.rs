@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum CreditSource {
+    #[sea_orm(string_value = "signup")]
+    Signup,
+    #[sea_orm(string_value = "purchase")]
+    Purchase,
+    #[sea_orm(string_value = "subscription")]
+    Subscription,
+    #[sea_orm(string_value = "promo")]
+    Promo,
+    #[sea_orm(string_value = "referral")]
+    Referral,
+    #[sea_orm(string_value = "refill")]
+    Refill,
+    /// Balance raised directly through `set_session`.
+    #[sea_orm(string_value = "adjustment")]
+    Adjustment,
+}
+
+/// Credits from one grant. Debits draw buckets down soonest-expiring first;
+/// whatever is left when a bucket expires is swept off the balance.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "credit_buckets")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    #[sea_orm(indexed)]
+    pub user_id: i64,
+    pub source: CreditSource,
+    pub granted: i64,
+    pub remaining: i64,
+    /// `None` for credits that never expire.
+    #[sea_orm(indexed)]
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::UserId",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
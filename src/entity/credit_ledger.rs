@@ -0,0 +1,69 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum LedgerEntryKind {
+    #[sea_orm(string_value = "subscription_start")]
+    SubscriptionStart,
+    #[sea_orm(string_value = "subscription_renewal")]
+    SubscriptionRenewal,
+    #[sea_orm(string_value = "payment")]
+    Payment,
+    #[sea_orm(string_value = "refund")]
+    Refund,
+    #[sea_orm(string_value = "promo")]
+    Promo,
+    #[sea_orm(string_value = "referral")]
+    Referral,
+    #[sea_orm(string_value = "refill")]
+    Refill,
+    #[sea_orm(string_value = "expiry")]
+    Expiry,
+    #[sea_orm(string_value = "usage")]
+    Usage,
+    /// A manual correction by support.
+    #[sea_orm(string_value = "adjustment")]
+    Adjustment,
+}
+
+/// Append-only record of every change to a user's credit balance.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "credit_ledger")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    #[sea_orm(indexed)]
+    pub user_id: i64,
+    /// Positive for grants, negative for debits.
+    pub amount: i64,
+    pub kind: LedgerEntryKind,
+    /// Identifies the event that caused the entry; a second entry for the same
+    /// event is rejected by the unique index.
+    #[sea_orm(unique)]
+    pub idempotency_key: String,
+    /// What the entry was for, e.g. the priced usage behind a debit.
+    pub details: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::UserId",
+        on_delete = "Cascade"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,2 +1,12 @@
+pub mod audit_log;
+pub mod credit_bucket;
+pub mod credit_ledger;
+pub mod model_price;
+pub mod payment;
+pub mod plan;
+pub mod promo_code;
+pub mod promo_redemption;
+pub mod referral;
 pub mod session;
+pub mod subscription;
 pub mod user;
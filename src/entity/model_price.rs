@@ -0,0 +1,43 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, EnumIter, DeriveActiveEnum, Serialize, Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum PriceUnit {
+    #[sea_orm(string_value = "request")]
+    Request,
+    #[sea_orm(string_value = "input_token")]
+    InputToken,
+    #[sea_orm(string_value = "output_token")]
+    OutputToken,
+    #[sea_orm(string_value = "image")]
+    Image,
+}
+
+/// What one unit of a model's usage costs from `effective_from` until a later
+/// price for the same model and unit takes over.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "model_prices")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    #[sea_orm(indexed)]
+    pub model: String,
+    pub unit: PriceUnit,
+    /// Credits charged per `per_units` units, so token prices can be quoted
+    /// per thousand or million tokens without fractions.
+    pub credits: i64,
+    pub per_units: i64,
+    pub effective_from: DateTime<Utc>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
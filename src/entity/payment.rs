@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+    #[sea_orm(string_value = "paid")]
+    Paid,
+    #[sea_orm(string_value = "refunded")]
+    Refunded,
+    /// Taken by Telegram but matching no plan, so nothing was granted; kept
+    /// for support to refund.
+    #[sea_orm(string_value = "unmatched")]
+    Unmatched,
+    /// Claimed for an admin refund that Telegram hasn't confirmed yet; the
+    /// refund is retried until it has.
+    #[sea_orm(string_value = "refund_pending")]
+    RefundPending,
+}
+
+/// A Telegram Stars payment and what it paid for.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "payments")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    #[sea_orm(indexed)]
+    pub user_id: i64,
+    /// `None` for unmatched payments whose payload names no known plan.
+    pub plan_id: Option<Uuid>,
+    /// Needed to refund the payment through the Bot API.
+    #[sea_orm(unique)]
+    pub telegram_payment_charge_id: String,
+    pub provider_payment_charge_id: String,
+    pub currency: String,
+    pub total_amount: i64,
+    pub invoice_payload: String,
+    pub status: PaymentStatus,
+    /// The subscription this payment started or extended, if any.
+    pub subscription_id: Option<Uuid>,
+    pub credits_granted: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::UserId",
+        to = "super::user::Column::UserId",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::plan::Entity",
+        from = "Column::PlanId",
+        to = "super::plan::Column::Id"
+    )]
+    Plan,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::plan::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Plan.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,171 @@
+use chrono::{DateTime, Datelike, Months, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, DeriveActiveEnum, Serialize, Deserialize)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum BillingPeriod {
+    #[sea_orm(string_value = "monthly")]
+    Monthly,
+    #[sea_orm(string_value = "yearly")]
+    Yearly,
+    /// Credit packs: buying one grants `credits_per_period` once and starts no
+    /// subscription.
+    #[sea_orm(string_value = "one_time")]
+    OneTime,
+}
+
+impl BillingPeriod {
+    fn months(self) -> u32 {
+        match self {
+            Self::Monthly => 1,
+            Self::Yearly => 12,
+            Self::OneTime => 0,
+        }
+    }
+
+    /// End of a period that starts at `start`.
+    pub fn advance(self, start: DateTime<Utc>) -> DateTime<Utc> {
+        start
+            .checked_add_months(Months::new(self.months()))
+            .unwrap_or(start)
+    }
+
+    /// End of the first period counted from `started_at` that ends after both
+    /// `period_end` and `now`, skipping whole periods that ended in between.
+    /// Counting from the start keeps the day of month a subscription began
+    /// on, rather than carrying over the clamp of a short month.
+    pub fn next_period_end(
+        self,
+        started_at: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        let months = self.months();
+        if months == 0 {
+            return period_end;
+        }
+        let after = period_end.max(now);
+        let elapsed_months = (after.year() - started_at.year()) * 12 + after.month() as i32
+            - started_at.month() as i32;
+        let mut periods = (elapsed_months.max(0) as u32 / months).max(1);
+        loop {
+            match started_at.checked_add_months(Months::new(periods * months)) {
+                Some(end) if end > after => return end,
+                Some(_) => periods += 1,
+                None => return period_end,
+            }
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "plans")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    /// Stable identifier clients and payment payloads refer to, e.g. `pro_monthly`.
+    #[sea_orm(unique, indexed)]
+    pub code: String,
+    pub name: String,
+    pub tier: String,
+    /// In the smallest unit of `currency`.
+    pub price: i64,
+    pub currency: String,
+    pub period: BillingPeriod,
+    pub credits_per_period: i64,
+    pub features: Json,
+    /// Inactive plans keep serving existing subscribers but can't be newly bought.
+    pub is_active: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(has_many = "super::subscription::Entity")]
+    Subscription,
+}
+
+impl Related<super::subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Subscription.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn next_period_end_moves_one_period_when_current() {
+        assert_eq!(
+            BillingPeriod::Monthly.next_period_end(
+                at(2026, 1, 15),
+                at(2026, 2, 15),
+                at(2026, 2, 15)
+            ),
+            at(2026, 3, 15)
+        );
+    }
+
+    #[test]
+    fn next_period_end_recovers_after_february() {
+        let started_at = at(2026, 1, 31);
+        let mut period_end = BillingPeriod::Monthly.advance(started_at);
+        assert_eq!(period_end, at(2026, 2, 28));
+        for expected in [at(2026, 3, 31), at(2026, 4, 30), at(2026, 5, 31)] {
+            period_end = BillingPeriod::Monthly.next_period_end(started_at, period_end, period_end);
+            assert_eq!(period_end, expected);
+        }
+
+        assert_eq!(
+            BillingPeriod::Yearly.next_period_end(
+                at(2024, 2, 29),
+                at(2025, 2, 28),
+                at(2025, 2, 28)
+            ),
+            at(2026, 2, 28)
+        );
+        assert_eq!(
+            BillingPeriod::Yearly.next_period_end(
+                at(2024, 2, 29),
+                at(2027, 2, 28),
+                at(2027, 2, 28)
+            ),
+            at(2028, 2, 29)
+        );
+    }
+
+    #[test]
+    fn next_period_end_skips_missed_periods() {
+        assert_eq!(
+            BillingPeriod::Monthly.next_period_end(
+                at(2026, 1, 31),
+                at(2026, 2, 28),
+                at(2026, 5, 20)
+            ),
+            at(2026, 5, 31)
+        );
+        assert_eq!(
+            BillingPeriod::Yearly.next_period_end(at(2022, 3, 1), at(2023, 3, 1), at(2026, 3, 1)),
+            at(2027, 3, 1)
+        );
+    }
+
+    #[test]
+    fn next_period_end_stops_for_one_time_plans() {
+        assert_eq!(
+            BillingPeriod::OneTime.next_period_end(at(2026, 1, 1), at(2026, 1, 1), at(2026, 5, 1)),
+            at(2026, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// A code users can redeem for bonus credits, a trial subscription, or both.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize, DeriveEntityModel)]
+#[sea_orm(table_name = "promo_codes")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: Uuid,
+    /// Stored upper-cased; redemption is case-insensitive.
+    #[sea_orm(unique, indexed)]
+    pub code: String,
+    pub credits: i64,
+    /// Plan a trial subscription is started on, if the code grants one.
+    pub plan_id: Option<Uuid>,
+    /// Trial length; one period of the plan when unset.
+    pub trial_days: Option<i32>,
+    /// Total redemptions across all users; unlimited when unset.
+    pub max_redemptions: Option<i64>,
+    pub per_user_limit: i32,
+    pub redemption_count: i64,
+    pub valid_from: Option<DateTime<Utc>>,
+    pub valid_until: Option<DateTime<Utc>>,
+    pub is_active: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::plan::Entity",
+        from = "Column::PlanId",
+        to = "super::plan::Column::Id"
+    )]
+    Plan,
+    #[sea_orm(has_many = "super::promo_redemption::Entity")]
+    PromoRedemption,
+}
+
+impl Related<super::plan::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Plan.def()
+    }
+}
+
+impl Related<super::promo_redemption::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PromoRedemption.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
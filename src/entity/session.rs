@@ -14,6 +14,15 @@ pub struct Model {
     pub last_active_timestamp: i64,
     pub preferences: serde_json::Value,
     pub session_metadata: serde_json::Value,
+    /// When the free credit refill last ran for this user.
+    pub last_refill_at: Option<DateTime<Utc>>,
+    /// Mirrors the user's status so auth checks can use the cached session.
+    pub status: super::user::UserStatus,
+    pub suspended_until: Option<DateTime<Utc>>,
+    /// Tokens issued at or before this moment are rejected.
+    pub tokens_revoked_at: Option<DateTime<Utc>>,
+    /// Incremented on every write; updates only apply against the version they read.
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
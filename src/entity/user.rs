@@ -1,5 +1,30 @@
 use chrono::{DateTime, Utc};
 use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    EnumIter,
+    DeriveActiveEnum,
+    Serialize,
+    Deserialize,
+)]
+#[sea_orm(rs_type = "String", db_type = "String(StringLen::None)")]
+#[serde(rename_all = "snake_case")]
+pub enum UserStatus {
+    #[sea_orm(string_value = "active")]
+    Active,
+    /// Blocked until `suspended_until`, then active again.
+    #[sea_orm(string_value = "suspended")]
+    Suspended,
+    #[sea_orm(string_value = "banned")]
+    Banned,
+}
 
 #[derive(Debug, PartialEq, PartialOrd, Clone, DeriveEntityModel)]
 #[sea_orm(table_name = "users")]
@@ -8,9 +33,18 @@ pub struct Model {
     pub id: Uuid,
     #[sea_orm(unique, indexed)]
     pub user_id: i64,
+    /// Telegram username without the `@`, as of the user's last login.
+    #[sea_orm(indexed)]
+    pub username: Option<String>,
     pub total_credits: i64,
     pub credits_remaining: i64,
     pub subscription_status: bool,
+    pub status: UserStatus,
+    pub suspended_until: Option<DateTime<Utc>>,
+    /// Why support suspended or banned the user.
+    pub status_reason: Option<String>,
+    /// Incremented on every write; updates only apply against the version they read.
+    pub version: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -19,10 +53,17 @@ pub struct Model {
 pub enum Relation {
     #[sea_orm(has_many = "super::session::Entity")]
     Session,
+    #[sea_orm(has_many = "super::subscription::Entity")]
+    Subscription,
 }
 impl Related<super::session::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Session.def()
     }
 }
+impl Related<super::subscription::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Subscription.def()
+    }
+}
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,83 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use sea_orm::TransactionTrait;
+use tracing::{error, info};
+
+use crate::{
+    entity::credit_bucket,
+    repositories,
+    utils::{self, lock::try_advisory_xact_lock, session::SessionKey, transaction::UnitOfWork},
+    ServiceState,
+};
+
+/// Advisory lock key held by whichever replica is running the expiry sweep.
+const EXPIRY_LOCK_KEY: i64 = 0x4352_4544_4558_5052;
+
+/// Takes whatever is left in expired credit buckets off the balance.
+///
+/// Each bucket is emptied only if it still holds what it was read with, and
+/// the ledger entry is keyed by bucket, so overlapping sweeps and debits never
+/// remove the same credits twice.
+pub async fn run(state: Arc<ServiceState>) -> Result<(), String> {
+    let lock = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start lock transaction: {}", e))?;
+    if !try_advisory_xact_lock(&lock, EXPIRY_LOCK_KEY).await? {
+        info!("Credit expiry is already running on another instance");
+        return Ok(());
+    }
+
+    let expired = repositories::credit_bucket::find_expired(
+        state.db.as_ref(),
+        Utc::now(),
+        state.config.jobs.credit_expiry_batch_size,
+    )
+    .await?;
+    if !expired.is_empty() {
+        info!("Expiring {} credit buckets", expired.len());
+    }
+    for bucket in expired {
+        if let Err(e) = process(state.clone(), &bucket).await {
+            metrics::counter!("credit_expiry_job_failures_total").increment(1);
+            error!(
+                "Failed to expire credit bucket {} for user ID {}: {}",
+                bucket.id, bucket.user_id, e
+            );
+        }
+    }
+
+    lock.commit()
+        .await
+        .map_err(|e| format!("Failed to release credit expiry lock: {}", e))
+}
+
+async fn process(state: Arc<ServiceState>, bucket: &credit_bucket::Model) -> Result<(), String> {
+    let now = Utc::now();
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| format!("Failed to start a database transaction: {}", e))?;
+
+    let (expired, session) = utils::credits::expire(&transaction, bucket, now).await?;
+    if let Some(session) = session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey {
+                    user_id: session.user_id,
+                },
+                session,
+            ),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+    metrics::counter!("credits_expired_total").increment(expired as u64);
+    Ok(())
+}
@@ -0,0 +1,70 @@
+pub mod credit_expiry;
+pub mod refill;
+pub mod subscriptions;
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::time::MissedTickBehavior;
+use tracing::{error, info};
+
+use crate::ServiceState;
+
+/// Starts the background jobs. Every replica runs them; jobs that must not
+/// overlap serialize themselves with a Postgres advisory lock.
+pub fn spawn_all(state: Arc<ServiceState>) {
+    if !state.config.jobs.enabled {
+        info!("Background jobs are disabled");
+        return;
+    }
+
+    spawn_periodic(
+        "subscription_renewal",
+        state.config.jobs.subscription_interval,
+        state.clone(),
+        subscriptions::run,
+    );
+
+    spawn_periodic(
+        "credit_expiry",
+        state.config.jobs.credit_expiry_interval,
+        state.clone(),
+        credit_expiry::run,
+    );
+
+    if state.config.refill.is_enabled() {
+        spawn_periodic(
+            "credit_refill",
+            state.config.jobs.refill_interval,
+            state.clone(),
+            refill::run,
+        );
+    }
+}
+
+fn spawn_periodic<F, Fut>(name: &'static str, period: Duration, state: Arc<ServiceState>, job: F)
+where
+    F: Fn(Arc<ServiceState>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), String>> + Send,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let started = Instant::now();
+            let outcome = match job(state.clone()).await {
+                Ok(()) => "ok",
+                Err(e) => {
+                    error!("Background job '{}' failed: {}", name, e);
+                    "error"
+                }
+            };
+            metrics::counter!("job_runs_total", "job" => name, "outcome" => outcome).increment(1);
+            metrics::histogram!("job_duration_seconds", "job" => name)
+                .record(started.elapsed().as_secs_f64());
+        }
+    });
+    info!("✔ Background job '{}' scheduled every {:?}", name, period);
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use sea_orm::TransactionTrait;
+use tracing::{error, info};
+
+use crate::{
+    entity::{credit_bucket::CreditSource, credit_ledger::LedgerEntryKind, session},
+    repositories,
+    utils::{self, lock::try_advisory_xact_lock, session::SessionKey, transaction::UnitOfWork},
+    ServiceState,
+};
+
+/// Advisory lock key held by whichever replica is running the refill pass.
+const REFILL_LOCK_KEY: i64 = 0x5245_4649_4c4c_4352;
+
+/// Tops up every qualifying balance whose refill period has elapsed.
+///
+/// A session's refill is claimed by moving its `last_refill_at` from the value
+/// it was read with, and the grant is keyed by that value in the ledger, so a
+/// retried or racing pass never grants the same period twice. Balances at the
+/// cap are left alone and become due as soon as they drop below it.
+pub async fn run(state: Arc<ServiceState>) -> Result<(), String> {
+    let config = &state.config.refill;
+    if !config.is_enabled() {
+        return Ok(());
+    }
+
+    let lock = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start lock transaction: {}", e))?;
+    if !try_advisory_xact_lock(&lock, REFILL_LOCK_KEY).await? {
+        info!("Credit refill is already running on another instance");
+        return Ok(());
+    }
+
+    let due = repositories::session::find_due_refills(
+        state.db.as_ref(),
+        Utc::now() - config.period,
+        config.cap,
+        config.non_subscribers_only,
+        state.config.jobs.refill_batch_size,
+    )
+    .await?;
+    if !due.is_empty() {
+        info!("Refilling {} sessions", due.len());
+    }
+    for session in due {
+        if let Err(e) = process(state.clone(), &session).await {
+            metrics::counter!("refill_job_failures_total").increment(1);
+            error!(
+                "Failed to refill credits for user ID {}: {}",
+                session.user_id, e
+            );
+        }
+    }
+
+    lock.commit()
+        .await
+        .map_err(|e| format!("Failed to release refill lock: {}", e))
+}
+
+async fn process(state: Arc<ServiceState>, session: &session::Model) -> Result<(), String> {
+    let config = &state.config.refill;
+    let now = Utc::now();
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| format!("Failed to start a database transaction: {}", e))?;
+
+    let Some(claimed) =
+        repositories::session::claim_refill(&transaction, session.id, session.last_refill_at, now)
+            .await?
+    else {
+        return Ok(());
+    };
+    // Subscribers get nothing, but their claim is still committed so they
+    // aren't picked up again on every pass.
+    let excluded = config.non_subscribers_only
+        && utils::billing::derived_subscription_status(&*transaction, session.user_id, now)
+            .await?
+            .unwrap_or(claimed.subscription_status);
+
+    let amount = if excluded {
+        0
+    } else {
+        utils::refill::refill_amount(config, claimed.credits_remaining)
+    };
+    let granted = amount > 0
+        && repositories::credit_ledger::record(
+            &transaction,
+            session.user_id,
+            amount,
+            LedgerEntryKind::Refill,
+            format!(
+                "refill:{}:{}",
+                session.user_id,
+                session
+                    .last_refill_at
+                    .unwrap_or(session.created_at)
+                    .timestamp()
+            ),
+            now,
+        )
+        .await?;
+
+    let session = if granted {
+        metrics::counter!("credits_refilled_total").increment(amount as u64);
+        utils::credits::grant(
+            &transaction,
+            session.user_id,
+            amount,
+            CreditSource::Refill,
+            state
+                .config
+                .billing
+                .credit_expiry(CreditSource::Refill, now),
+            now,
+        )
+        .await?
+    } else {
+        Some(claimed)
+    };
+
+    if let Some(session) = session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey {
+                    user_id: session.user_id,
+                },
+                session,
+            ),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use chrono::Utc;
+use sea_orm::TransactionTrait;
+use tracing::{error, info};
+
+use crate::{
+    entity::{
+        credit_bucket::CreditSource,
+        credit_ledger::LedgerEntryKind,
+        subscription::{self, SubscriptionRenewal},
+    },
+    repositories,
+    utils::{self, lock::try_advisory_xact_lock, session::SessionKey, transaction::UnitOfWork},
+    ServiceState,
+};
+
+/// Advisory lock key held by whichever replica is running the renewal pass.
+const RENEWAL_LOCK_KEY: i64 = 0x5355_4252_454e_4557;
+
+/// Renews or expires every subscription whose period has ended. Only
+/// subscriptions with automatic renewal that weren't canceled are renewed.
+///
+/// Each subscription is handled in its own transaction whose updates are
+/// conditioned on the period it was read with, and credit grants are keyed by
+/// subscription and period in the ledger, so a pass that is retried or races
+/// with another replica never grants the same period twice.
+pub async fn run(state: Arc<ServiceState>) -> Result<(), String> {
+    let lock = state
+        .db
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start lock transaction: {}", e))?;
+    if !try_advisory_xact_lock(&lock, RENEWAL_LOCK_KEY).await? {
+        info!("Subscription renewal is already running on another instance");
+        return Ok(());
+    }
+
+    let due = repositories::subscription::find_due(
+        state.db.as_ref(),
+        Utc::now(),
+        state.config.jobs.subscription_batch_size,
+    )
+    .await?;
+    if !due.is_empty() {
+        info!("Processing {} due subscriptions", due.len());
+    }
+    for subscription in due {
+        if let Err(e) = process(state.clone(), &subscription).await {
+            metrics::counter!("subscription_job_failures_total").increment(1);
+            error!(
+                "Failed to process subscription {} for user ID {}: {}",
+                subscription.id, subscription.user_id, e
+            );
+        }
+    }
+
+    lock.commit()
+        .await
+        .map_err(|e| format!("Failed to release renewal lock: {}", e))
+}
+
+async fn process(
+    state: Arc<ServiceState>,
+    subscription: &subscription::Model,
+) -> Result<(), String> {
+    let now = Utc::now();
+    let mut transaction = UnitOfWork::begin(&state.db)
+        .await
+        .map_err(|e| format!("Failed to start a database transaction: {}", e))?;
+
+    // Only subscriptions billed outside the service renew here; the others
+    // are extended by their next payment, if one comes.
+    let renews =
+        subscription.renewal == SubscriptionRenewal::Auto && !subscription.cancel_at_period_end;
+    let (granted_credits, period_end) = if !renews {
+        if !repositories::subscription::expire(
+            &transaction,
+            subscription.id,
+            subscription.current_period_end,
+            now,
+        )
+        .await?
+        {
+            return Ok(());
+        }
+        metrics::counter!("subscriptions_expired_total").increment(1);
+        (0, None)
+    } else {
+        let plan = repositories::plan::find_by_id(&*transaction, subscription.plan_id)
+            .await?
+            .ok_or_else(|| format!("Plan {} not found", subscription.plan_id))?;
+        // A subscription that fell behind, e.g. while jobs were off, skips
+        // the missed periods and is credited for the new one only.
+        let period_end = plan.period.next_period_end(
+            subscription.started_at,
+            subscription.current_period_end,
+            now,
+        );
+        if !repositories::subscription::renew(
+            &transaction,
+            subscription.id,
+            subscription.current_period_end,
+            period_end,
+            now,
+        )
+        .await?
+        {
+            return Ok(());
+        }
+        metrics::counter!("subscriptions_renewed_total").increment(1);
+        let granted = repositories::credit_ledger::record(
+            &transaction,
+            subscription.user_id,
+            plan.credits_per_period,
+            LedgerEntryKind::SubscriptionRenewal,
+            format!(
+                "subscription:{}:renewal:{}",
+                subscription.id,
+                subscription.current_period_end.timestamp()
+            ),
+            now,
+        )
+        .await?;
+        if granted {
+            (plan.credits_per_period, Some(period_end))
+        } else {
+            (0, None)
+        }
+    };
+
+    // Credits granted for a period expire with it.
+    let session = utils::credits::grant(
+        &transaction,
+        subscription.user_id,
+        granted_credits,
+        CreditSource::Subscription,
+        period_end,
+        now,
+    )
+    .await?;
+
+    if let Some(session) = session {
+        transaction.after_commit(
+            "refresh_session_cache",
+            utils::session::write_after_commit(
+                state.clone(),
+                SessionKey {
+                    user_id: subscription.user_id,
+                },
+                session,
+            ),
+        );
+    }
+
+    transaction
+        .commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))
+}
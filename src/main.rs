@@ -3,18 +3,23 @@ mod config;
 mod controllers;
 mod dto;
 mod entity;
+mod jobs;
 mod repositories;
 mod routes;
 mod utils;
 
 use crate::{
     client::{
+        cache::{self, CacheClient},
         db::{DatabaseClient, DatabaseClientExt},
-        redis::{RedisClient, RedisClientBuilder},
+        telegram::{TelegramApi, TelegramBotClient},
     },
-    config::{tracing::subscribe_tracing, ServiceConfig},
-    routes::create_router,
+    config::{metrics::install_metrics_recorder, tracing::subscribe_tracing, ServiceConfig},
+    routes::{create_router, metrics},
+    utils::{invalidation::InvalidationBus, singleflight::SingleFlight},
 };
+use metrics_exporter_prometheus::PrometheusHandle;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing::{error, info};
 
@@ -22,12 +27,22 @@ use tracing::{error, info};
 pub struct ServiceState {
     pub config: Arc<ServiceConfig>,
     pub db: Arc<DatabaseClient>,
-    pub redis: Arc<RedisClient>,
+    /// Read replica for read-only queries; the primary when none is configured.
+    pub db_read: Arc<DatabaseClient>,
+    pub cache: Arc<CacheClient>,
+    pub invalidation: Arc<InvalidationBus>,
+    pub metrics: PrometheusHandle,
+    pub session_loads: Arc<SingleFlight>,
+    pub telegram: Arc<dyn TelegramApi>,
 }
 
 #[tokio::main]
 async fn main() -> Result<(), String> {
     subscribe_tracing();
+    let metrics_handle = install_metrics_recorder().map_err(|e| {
+        error!("💥 Error in installing metrics recorder: {}", e);
+        e
+    })?;
 
     let mut service_config = config::ServiceConfig::default();
     service_config.init_from_env().map_err(|e| {
@@ -44,18 +59,57 @@ async fn main() -> Result<(), String> {
         })?;
     info!("✔ Connected to the database!");
 
-    let redis_client = RedisClient::build_from_config(&service_config).map_err(|e| {
-        error!("💥 Error in redis connection: {}", e);
-        "Failed to build redis client"
+    let db_read_client = DatabaseClient::build_read_replica_from_config(&service_config)
+        .await
+        .map_err(|e| {
+            error!("💥 Error in read replica connection: {}", e);
+            "Failed to build read replica client"
+        })?;
+    if db_read_client.is_some() {
+        info!("✔ Connected to the read replica!");
+    }
+
+    let cache_client = cache::build_from_config(&service_config)
+        .await
+        .map_err(|e| {
+            error!("💥 Error in cache connection: {}", e);
+            "Failed to build cache client"
+        })?;
+    cache_client.ping().await.map_err(|e| {
+        error!("💥 Error in cache connection: {}", e);
+        "Failed to reach cache backend"
+    })?;
+    info!(
+        "✔ Connected to the {:?} cache backend!",
+        service_config.cache.backend
+    );
+
+    let telegram_client = TelegramBotClient::build_from_config(&service_config).map_err(|e| {
+        error!("💥 Error in building Telegram client: {}", e);
+        "Failed to build Telegram client"
     })?;
-    info!("✔ Connected to the Redis!");
 
+    let invalidation_bus = InvalidationBus::new(
+        cache_client.clone(),
+        service_config.cache.invalidation_channel.clone(),
+    );
+
+    let db_client = Arc::new(db_client);
     let service_state = Arc::new(ServiceState {
         config: Arc::new(service_config.clone()),
-        db: Arc::new(db_client),
-        redis: Arc::new(redis_client),
+        db_read: db_read_client
+            .map(Arc::new)
+            .unwrap_or_else(|| db_client.clone()),
+        db: db_client,
+        cache: cache_client,
+        invalidation: invalidation_bus,
+        metrics: metrics_handle,
+        session_loads: Arc::new(SingleFlight::default()),
+        telegram: Arc::new(telegram_client),
     });
 
+    jobs::spawn_all(service_state.clone());
+
     let listener_addr = service_config
         .clone()
         .server
@@ -77,8 +131,34 @@ async fn main() -> Result<(), String> {
     })?;
     info!("🚀 The server is listening on: {}", addr); // Move logging before serving
 
+    let metrics_addr = service_config
+        .server
+        .get_metrics_socket_addr()
+        .map_err(|e| {
+            error!("💥 Failed to get metrics socket address: {}", e);
+            "Invalid metrics socket address"
+        })?;
+    let metrics_listener = tokio::net::TcpListener::bind(metrics_addr)
+        .await
+        .map_err(|e| {
+            error!("💥 Failed to bind metrics listener: {}", e);
+            "Failed to bind metrics listener"
+        })?;
+    info!("📈 Metrics are served on: {}", metrics_addr);
+    let metrics_router = metrics::create_router(service_state.clone());
+    tokio::spawn(async move {
+        if let Err(e) = axum::serve(metrics_listener, metrics_router).await {
+            error!("💥 Metrics server error: {}", e);
+        }
+    });
+
     let router = create_router(service_state);
-    axum::serve(tcp_listener, router).await.map_err(|e| {
+    axum::serve(
+        tcp_listener,
+        router.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .map_err(|e| {
         error!("💥 Server error: {}", e);
         "Server error occurred"
     })?;
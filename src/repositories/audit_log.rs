@@ -0,0 +1,28 @@
+use crate::entity::audit_log;
+use chrono::{DateTime, Utc};
+use sea_orm::{ActiveModelTrait, DatabaseTransaction, Set};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn record(
+    tx: &DatabaseTransaction,
+    actor: &str,
+    action: &str,
+    target_user_id: Option<i64>,
+    details: serde_json::Value,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let entry = audit_log::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        actor: Set(actor.to_string()),
+        action: Set(action.to_string()),
+        target_user_id: Set(target_user_id),
+        details: Set(details),
+        created_at: Set(now),
+    };
+
+    match entry.insert(tx).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Audit entry was not saved successfully: {}", e)),
+    }
+}
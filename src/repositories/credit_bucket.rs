@@ -0,0 +1,155 @@
+use crate::entity::credit_bucket::{self, CreditSource};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, NullOrdering},
+    ActiveModelTrait, ColumnTrait, Condition, ConnectionTrait, DatabaseTransaction, EntityTrait,
+    Order, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    source: CreditSource,
+    expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<credit_bucket::Model, String> {
+    let new_bucket = credit_bucket::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        source: Set(source),
+        granted: Set(amount),
+        remaining: Set(amount),
+        expires_at: Set(expires_at),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    match new_bucket.insert(tx).await {
+        Ok(bucket) => Ok(bucket),
+        Err(e) => Err(format!("Credit bucket was not saved successfully: {}", e)),
+    }
+}
+
+fn unexpired(now: DateTime<Utc>) -> Condition {
+    Condition::any()
+        .add(credit_bucket::Column::ExpiresAt.is_null())
+        .add(credit_bucket::Column::ExpiresAt.gt(now))
+}
+
+/// The user's non-empty, unexpired buckets in the order debits consume them:
+/// soonest-expiring first, never-expiring last, oldest first among equals.
+#[tracing::instrument(skip_all)]
+pub async fn find_spendable_by_user_id(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<credit_bucket::Model>, String> {
+    match credit_bucket::Entity::find()
+        .filter(credit_bucket::Column::UserId.eq(user_id))
+        .filter(credit_bucket::Column::Remaining.gt(0))
+        .filter(unexpired(now))
+        .order_by_with_nulls(
+            credit_bucket::Column::ExpiresAt,
+            Order::Asc,
+            NullOrdering::Last,
+        )
+        .order_by_asc(credit_bucket::Column::CreatedAt)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding credit buckets by user_id: {}", e)),
+    }
+}
+
+/// Same as `find_spendable_by_user_id`, but row-locks the buckets for the rest
+/// of the transaction so concurrent debits queue up behind each other.
+#[tracing::instrument(skip_all)]
+pub async fn lock_spendable_by_user_id(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<credit_bucket::Model>, String> {
+    match credit_bucket::Entity::find()
+        .filter(credit_bucket::Column::UserId.eq(user_id))
+        .filter(credit_bucket::Column::Remaining.gt(0))
+        .filter(unexpired(now))
+        .order_by_with_nulls(
+            credit_bucket::Column::ExpiresAt,
+            Order::Asc,
+            NullOrdering::Last,
+        )
+        .order_by_asc(credit_bucket::Column::CreatedAt)
+        .lock_exclusive()
+        .all(tx)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error locking credit buckets by user_id: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn set_remaining(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    remaining: i64,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    match credit_bucket::Entity::update_many()
+        .col_expr(credit_bucket::Column::Remaining, Expr::value(remaining))
+        .col_expr(credit_bucket::Column::UpdatedAt, Expr::value(now))
+        .filter(credit_bucket::Column::Id.eq(id))
+        .exec(tx)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error updating credit bucket: {}", e)),
+    }
+}
+
+/// Expired buckets that still hold credits, soonest-expired first.
+#[tracing::instrument(skip_all)]
+pub async fn find_expired(
+    db: &impl ConnectionTrait,
+    now: DateTime<Utc>,
+    limit: u64,
+) -> Result<Vec<credit_bucket::Model>, String> {
+    match credit_bucket::Entity::find()
+        .filter(credit_bucket::Column::Remaining.gt(0))
+        .filter(credit_bucket::Column::ExpiresAt.lte(now))
+        .order_by_asc(credit_bucket::Column::ExpiresAt)
+        .limit(limit)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding expired credit buckets: {}", e)),
+    }
+}
+
+/// Empties an expired bucket if it still holds `remaining`. Returns `false`
+/// if a debit or another sweep changed it first.
+#[tracing::instrument(skip_all)]
+pub async fn expire(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    remaining: i64,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match credit_bucket::Entity::update_many()
+        .col_expr(credit_bucket::Column::Remaining, Expr::value(0))
+        .col_expr(credit_bucket::Column::UpdatedAt, Expr::value(now))
+        .filter(credit_bucket::Column::Id.eq(id))
+        .filter(credit_bucket::Column::Remaining.eq(remaining))
+        .filter(credit_bucket::Column::ExpiresAt.lte(now))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected == 1),
+        Err(e) => Err(format!("Error expiring credit bucket: {}", e)),
+    }
+}
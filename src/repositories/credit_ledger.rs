@@ -0,0 +1,94 @@
+use crate::entity::credit_ledger::{self, LedgerEntryKind};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::OnConflict, ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set, TryInsertResult,
+};
+use uuid::Uuid;
+
+/// Records a balance change. Returns `false` without writing anything when an
+/// entry with the same idempotency key already exists.
+pub async fn record(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    kind: LedgerEntryKind,
+    idempotency_key: String,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    record_with_details(tx, user_id, amount, kind, idempotency_key, None, now).await
+}
+
+/// Same as `record`, keeping `details` alongside the entry.
+#[tracing::instrument(skip_all)]
+pub async fn record_with_details(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    kind: LedgerEntryKind,
+    idempotency_key: String,
+    details: Option<serde_json::Value>,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    let entry = credit_ledger::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        amount: Set(amount),
+        kind: Set(kind),
+        idempotency_key: Set(idempotency_key),
+        details: Set(details),
+        created_at: Set(now),
+    };
+
+    match credit_ledger::Entity::insert(entry)
+        .on_conflict(
+            OnConflict::column(credit_ledger::Column::IdempotencyKey)
+                .do_nothing()
+                .to_owned(),
+        )
+        .do_nothing()
+        .exec(tx)
+        .await
+    {
+        Ok(TryInsertResult::Inserted(_)) => Ok(true),
+        Ok(_) => Ok(false),
+        Err(e) => Err(format!("Ledger entry was not saved successfully: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn find_by_idempotency_key(
+    db: &impl ConnectionTrait,
+    idempotency_key: &str,
+) -> Result<Option<credit_ledger::Model>, String> {
+    match credit_ledger::Entity::find()
+        .filter(credit_ledger::Column::IdempotencyKey.eq(idempotency_key))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!(
+            "Error finding ledger entry by idempotency key: {}",
+            e
+        )),
+    }
+}
+
+/// The user's latest ledger entries, newest first.
+#[tracing::instrument(skip_all)]
+pub async fn find_recent_by_user_id(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+    limit: u64,
+) -> Result<Vec<credit_ledger::Model>, String> {
+    match credit_ledger::Entity::find()
+        .filter(credit_ledger::Column::UserId.eq(user_id))
+        .order_by_desc(credit_ledger::Column::CreatedAt)
+        .limit(limit)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding recent ledger entries: {}", e)),
+    }
+}
@@ -0,0 +1,52 @@
+use crate::entity::model_price;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter,
+    QueryOrder,
+};
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    new_price: model_price::ActiveModel,
+) -> Result<model_price::Model, String> {
+    match new_price.insert(tx).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Model price was not saved successfully: {}", e)),
+    }
+}
+
+/// Every price for `model` that has taken effect by `at`, newest first.
+#[tracing::instrument(skip_all)]
+pub async fn find_effective_by_model(
+    db: &impl ConnectionTrait,
+    model: &str,
+    at: DateTime<Utc>,
+) -> Result<Vec<model_price::Model>, String> {
+    match model_price::Entity::find()
+        .filter(model_price::Column::Model.eq(model))
+        .filter(model_price::Column::EffectiveFrom.lte(at))
+        .order_by_desc(model_price::Column::EffectiveFrom)
+        .order_by_desc(model_price::Column::CreatedAt)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding prices for model: {}", e)),
+    }
+}
+
+/// The whole catalogue, including prices scheduled for the future.
+#[tracing::instrument(skip_all)]
+pub async fn find_all(db: &impl ConnectionTrait) -> Result<Vec<model_price::Model>, String> {
+    match model_price::Entity::find()
+        .order_by_asc(model_price::Column::Model)
+        .order_by_asc(model_price::Column::Unit)
+        .order_by_desc(model_price::Column::EffectiveFrom)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error listing model prices: {}", e)),
+    }
+}
@@ -0,0 +1,103 @@
+use crate::entity::payment::{self, PaymentStatus};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction,
+    EntityTrait, PaginatorTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    new_payment: payment::ActiveModel,
+) -> Result<payment::Model, String> {
+    match new_payment.insert(tx).await {
+        Ok(payment) => Ok(payment),
+        Err(e) => Err(format!("Payment record was not saved successfully: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn find_by_telegram_charge_id(
+    db: &impl ConnectionTrait,
+    telegram_payment_charge_id: &str,
+) -> Result<Option<payment::Model>, String> {
+    match payment::Entity::find()
+        .filter(payment::Column::TelegramPaymentChargeId.eq(telegram_payment_charge_id))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding payment by charge id: {}", e)),
+    }
+}
+
+/// Claims a paid or unmatched payment for a refund. Returns `false` if it is
+/// no longer in either state, e.g. because a concurrent refund got there first.
+#[tracing::instrument(skip_all)]
+pub async fn mark_refund_pending(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match payment::Entity::update_many()
+        .col_expr(
+            payment::Column::Status,
+            Expr::value(PaymentStatus::RefundPending),
+        )
+        .col_expr(payment::Column::UpdatedAt, Expr::value(now))
+        .filter(payment::Column::Id.eq(id))
+        .filter(payment::Column::Status.is_in([PaymentStatus::Paid, PaymentStatus::Unmatched]))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error marking payment refund pending: {}", e)),
+    }
+}
+
+/// Flips a paid, unmatched or refund-pending payment to refunded. Returns
+/// `false` if it was already refunded, e.g. because a concurrent refund got
+/// there first.
+#[tracing::instrument(skip_all)]
+pub async fn mark_refunded(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match payment::Entity::update_many()
+        .col_expr(
+            payment::Column::Status,
+            Expr::value(PaymentStatus::Refunded),
+        )
+        .col_expr(payment::Column::UpdatedAt, Expr::value(now))
+        .filter(payment::Column::Id.eq(id))
+        .filter(payment::Column::Status.is_in([
+            PaymentStatus::Paid,
+            PaymentStatus::Unmatched,
+            PaymentStatus::RefundPending,
+        ]))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error marking payment refunded: {}", e)),
+    }
+}
+
+/// Counts the payments for `subscription_id` that are still paid.
+#[tracing::instrument(skip_all)]
+pub async fn count_paid_by_subscription_id(
+    db: &impl ConnectionTrait,
+    subscription_id: Uuid,
+) -> Result<u64, String> {
+    match payment::Entity::find()
+        .filter(payment::Column::SubscriptionId.eq(subscription_id))
+        .filter(payment::Column::Status.eq(PaymentStatus::Paid))
+        .count(db)
+        .await
+    {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("Error counting payments for subscription: {}", e)),
+    }
+}
@@ -0,0 +1,46 @@
+use crate::entity::plan;
+use sea_orm::{ColumnTrait, ConnectionTrait, EntityTrait, QueryFilter};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn find_by_id(
+    db: &impl ConnectionTrait,
+    id: Uuid,
+) -> Result<Option<plan::Model>, String> {
+    match plan::Entity::find_by_id(id).one(db).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding plan by id: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn find_by_code(
+    db: &impl ConnectionTrait,
+    code: &str,
+) -> Result<Option<plan::Model>, String> {
+    match plan::Entity::find()
+        .filter(plan::Column::Code.eq(code))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding plan by code: {}", e)),
+    }
+}
+
+/// Looks up a plan that can still be subscribed to.
+#[tracing::instrument(skip_all)]
+pub async fn find_active_by_code(
+    db: &impl ConnectionTrait,
+    code: &str,
+) -> Result<Option<plan::Model>, String> {
+    match plan::Entity::find()
+        .filter(plan::Column::Code.eq(code))
+        .filter(plan::Column::IsActive.eq(true))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding plan by code: {}", e)),
+    }
+}
@@ -0,0 +1,95 @@
+use crate::entity::promo_code;
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Condition, Expr},
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter,
+    Set,
+};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    new_code: promo_code::ActiveModel,
+) -> Result<promo_code::Model, String> {
+    match new_code.insert(tx).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Promo code was not saved successfully: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn find_by_code(
+    db: &impl ConnectionTrait,
+    code: &str,
+) -> Result<Option<promo_code::Model>, String> {
+    match promo_code::Entity::find()
+        .filter(promo_code::Column::Code.eq(code.to_uppercase()))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding promo code: {}", e)),
+    }
+}
+
+/// Counts one more redemption if the code is active, inside its validity
+/// window and under its redemption cap at `now`. The update also row-locks the
+/// code until the transaction ends, serializing concurrent redemptions.
+#[tracing::instrument(skip_all)]
+pub async fn claim_redemption(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match promo_code::Entity::update_many()
+        .col_expr(
+            promo_code::Column::RedemptionCount,
+            Expr::col(promo_code::Column::RedemptionCount).add(1),
+        )
+        .col_expr(promo_code::Column::UpdatedAt, Expr::value(now))
+        .filter(promo_code::Column::Id.eq(id))
+        .filter(promo_code::Column::IsActive.eq(true))
+        .filter(
+            Condition::any()
+                .add(promo_code::Column::ValidFrom.is_null())
+                .add(promo_code::Column::ValidFrom.lte(now)),
+        )
+        .filter(
+            Condition::any()
+                .add(promo_code::Column::ValidUntil.is_null())
+                .add(promo_code::Column::ValidUntil.gt(now)),
+        )
+        .filter(
+            Condition::any()
+                .add(promo_code::Column::MaxRedemptions.is_null())
+                .add(
+                    Expr::col(promo_code::Column::RedemptionCount)
+                        .lt(Expr::col(promo_code::Column::MaxRedemptions)),
+                ),
+        )
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error claiming promo code redemption: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn deactivate(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    now: DateTime<Utc>,
+) -> Result<promo_code::Model, String> {
+    let model = promo_code::ActiveModel {
+        id: Set(id),
+        is_active: Set(false),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    match model.update(tx).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error deactivating promo code: {}", e)),
+    }
+}
@@ -0,0 +1,37 @@
+use crate::entity::promo_redemption;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait,
+    PaginatorTrait, QueryFilter,
+};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    new_redemption: promo_redemption::ActiveModel,
+) -> Result<promo_redemption::Model, String> {
+    match new_redemption.insert(tx).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!(
+            "Promo redemption was not saved successfully: {}",
+            e
+        )),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn count_by_user_id(
+    db: &impl ConnectionTrait,
+    promo_code_id: Uuid,
+    user_id: i64,
+) -> Result<u64, String> {
+    match promo_redemption::Entity::find()
+        .filter(promo_redemption::Column::PromoCodeId.eq(promo_code_id))
+        .filter(promo_redemption::Column::UserId.eq(user_id))
+        .count(db)
+        .await
+    {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("Error counting promo redemptions: {}", e)),
+    }
+}
@@ -0,0 +1,124 @@
+use crate::entity::referral::{self, ReferralStatus};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Alias, Expr},
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait,
+    PaginatorTrait, QueryFilter, QuerySelect,
+};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    new_referral: referral::ActiveModel,
+) -> Result<referral::Model, String> {
+    match new_referral.insert(tx).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Referral was not saved successfully: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn find_pending_by_invitee(
+    db: &impl ConnectionTrait,
+    invitee_user_id: i64,
+) -> Result<Option<referral::Model>, String> {
+    match referral::Entity::find()
+        .filter(referral::Column::InviteeUserId.eq(invitee_user_id))
+        .filter(referral::Column::Status.eq(ReferralStatus::Pending))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding pending referral: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn find_by_invitee(
+    db: &impl ConnectionTrait,
+    invitee_user_id: i64,
+) -> Result<Option<referral::Model>, String> {
+    match referral::Entity::find()
+        .filter(referral::Column::InviteeUserId.eq(invitee_user_id))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding referral by invitee: {}", e)),
+    }
+}
+
+/// Flips a pending referral to rewarded with the credits each side got.
+/// Returns `false` if it was already rewarded.
+#[tracing::instrument(skip_all)]
+pub async fn mark_rewarded(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    referrer_credits: i64,
+    invitee_credits: i64,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match referral::Entity::update_many()
+        .col_expr(
+            referral::Column::Status,
+            Expr::value(ReferralStatus::Rewarded),
+        )
+        .col_expr(
+            referral::Column::ReferrerCredits,
+            Expr::value(referrer_credits),
+        )
+        .col_expr(
+            referral::Column::InviteeCredits,
+            Expr::value(invitee_credits),
+        )
+        .col_expr(referral::Column::RewardedAt, Expr::value(now))
+        .filter(referral::Column::Id.eq(id))
+        .filter(referral::Column::Status.eq(ReferralStatus::Pending))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error marking referral rewarded: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn count_by_referrer(
+    db: &impl ConnectionTrait,
+    referrer_user_id: i64,
+) -> Result<u64, String> {
+    match referral::Entity::find()
+        .filter(referral::Column::ReferrerUserId.eq(referrer_user_id))
+        .count(db)
+        .await
+    {
+        Ok(count) => Ok(count),
+        Err(e) => Err(format!("Error counting referrals: {}", e)),
+    }
+}
+
+/// Total bonus credits a referrer has been paid.
+#[tracing::instrument(skip_all)]
+pub async fn sum_referrer_credits(
+    db: &impl ConnectionTrait,
+    referrer_user_id: i64,
+) -> Result<i64, String> {
+    match referral::Entity::find()
+        .select_only()
+        .column_as(
+            Expr::col(referral::Column::ReferrerCredits)
+                .sum()
+                .cast_as(Alias::new("BIGINT")),
+            "total",
+        )
+        .filter(referral::Column::ReferrerUserId.eq(referrer_user_id))
+        .filter(referral::Column::Status.eq(ReferralStatus::Rewarded))
+        .into_tuple::<Option<i64>>()
+        .one(db)
+        .await
+    {
+        Ok(total) => Ok(total.flatten().unwrap_or_default()),
+        Err(e) => Err(format!("Error summing referral credits: {}", e)),
+    }
+}
@@ -1,25 +1,32 @@
-use crate::entity::session;
-use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter, Set};
-use serde_json::json;
+use crate::entity::{session, user::UserStatus};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ActiveValue::NotSet, ColumnTrait, Condition,
+    ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
 use uuid::Uuid;
 
 #[tracing::instrument(skip_all)]
-pub async fn save(tx: &DatabaseTransaction, user_id: i64) -> Result<Uuid, String> {
+pub async fn save(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    credits: i64,
+    preferences: serde_json::Value,
+    session_metadata: serde_json::Value,
+) -> Result<Uuid, String> {
     let new_session = session::ActiveModel {
         id: Set(Uuid::new_v4()),
         user_id: Set(user_id),
-        credits_remaining: Set(15),
+        credits_remaining: Set(credits),
         subscription_status: Set(false),
+        version: Set(1),
         last_active_timestamp: Set(Utc::now().timestamp()),
-        preferences: Set(json!({
-            "default_mode": "GPT-4o",
-            "notifications": true
-        })),
-        session_metadata: Set(json!({
-            "last_mode_used": "GPT-4o",
-            "recent_actions": ["request_made"]
-        })),
+        preferences: Set(preferences),
+        session_metadata: Set(session_metadata),
+        last_refill_at: Set(None),
+        status: Set(UserStatus::Active),
+        suspended_until: Set(None),
+        tokens_revoked_at: Set(None),
         created_at: Set(Utc::now()),
         updated_at: Set(Utc::now()),
     };
@@ -44,16 +51,94 @@ pub async fn find_by_id(
 
 #[tracing::instrument(skip_all)]
 pub async fn find_by_user_id(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+) -> Result<Option<session::Model>, String> {
+    match session::Entity::find()
+        .filter(session::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding session by session_id: {}", e)),
+    }
+}
+
+/// Same as `find_by_user_id`, but row-locks the session for the rest of the
+/// transaction so the balance read stays current until it is written.
+#[tracing::instrument(skip_all)]
+pub async fn lock_by_user_id(
     tx: &DatabaseTransaction,
     user_id: i64,
 ) -> Result<Option<session::Model>, String> {
     match session::Entity::find()
         .filter(session::Column::UserId.eq(user_id))
+        .lock_exclusive()
         .one(tx)
         .await
     {
         Ok(model) => Ok(model),
-        Err(e) => Err(format!("Error finding session by session_id: {}", e)),
+        Err(e) => Err(format!("Error locking session by user_id: {}", e)),
+    }
+}
+
+/// Writes `model` only if the row is still at `expected_version`, bumping the
+/// version by one. Returns `None` when another writer got there first.
+#[tracing::instrument(skip_all)]
+pub async fn update_if_version(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    mut model: session::ActiveModel,
+    expected_version: i64,
+) -> Result<Option<session::Model>, String> {
+    model.id = NotSet;
+    model.version = Set(expected_version + 1);
+    match session::Entity::update_many()
+        .set(model)
+        .filter(session::Column::Id.eq(id))
+        .filter(session::Column::Version.eq(expected_version))
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error updating session with version check: {}", e)),
+    }
+}
+
+/// Adds `credit_delta` to the balance and sets the subscription flag, when
+/// given, in a single statement, bumping the version so that version-checked writers
+/// holding an older copy get a conflict.
+#[tracing::instrument(skip_all)]
+pub async fn apply_balance_change(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    credit_delta: i64,
+    subscription_status: Option<bool>,
+    now: DateTime<Utc>,
+) -> Result<Option<session::Model>, String> {
+    let mut update = session::Entity::update_many()
+        .col_expr(
+            session::Column::CreditsRemaining,
+            Expr::col(session::Column::CreditsRemaining).add(credit_delta),
+        )
+        .col_expr(
+            session::Column::Version,
+            Expr::col(session::Column::Version).add(1),
+        )
+        .col_expr(session::Column::UpdatedAt, Expr::value(now));
+    if let Some(subscription_status) = subscription_status {
+        update = update.col_expr(
+            session::Column::SubscriptionStatus,
+            Expr::value(subscription_status),
+        );
+    }
+    match update
+        .filter(session::Column::UserId.eq(user_id))
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error applying balance change to session: {}", e)),
     }
 }
 
@@ -69,3 +154,104 @@ pub async fn exist_by_user_id(tx: &DatabaseTransaction, user_id: i64) -> Result<
         Err(e) => Err(format!("Error checking existence by user_id: {}", e)),
     }
 }
+
+/// Sessions below `cap` whose last refill (or signup, if they were never
+/// refilled) is at or before `due_before`, oldest first.
+#[tracing::instrument(skip_all)]
+pub async fn find_due_refills(
+    db: &impl ConnectionTrait,
+    due_before: DateTime<Utc>,
+    cap: i64,
+    non_subscribers_only: bool,
+    limit: u64,
+) -> Result<Vec<session::Model>, String> {
+    let mut query = session::Entity::find()
+        .filter(session::Column::CreditsRemaining.lt(cap))
+        .filter(
+            Condition::any()
+                .add(session::Column::LastRefillAt.lte(due_before))
+                .add(
+                    Condition::all()
+                        .add(session::Column::LastRefillAt.is_null())
+                        .add(session::Column::CreatedAt.lte(due_before)),
+                ),
+        );
+    if non_subscribers_only {
+        query = query.filter(session::Column::SubscriptionStatus.eq(false));
+    }
+    match query
+        .order_by_asc(session::Column::LastRefillAt)
+        .limit(limit)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding sessions due a refill: {}", e)),
+    }
+}
+
+/// Stamps the session as refilled at `now` if its last refill is still
+/// `last_refill_at`, bumping the version. Returns `None` when another pass
+/// already refilled it.
+#[tracing::instrument(skip_all)]
+pub async fn claim_refill(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    last_refill_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<Option<session::Model>, String> {
+    let previous = match last_refill_at {
+        Some(last_refill_at) => session::Column::LastRefillAt.eq(last_refill_at),
+        None => session::Column::LastRefillAt.is_null(),
+    };
+    match session::Entity::update_many()
+        .col_expr(session::Column::LastRefillAt, Expr::value(now))
+        .col_expr(
+            session::Column::Version,
+            Expr::col(session::Column::Version).add(1),
+        )
+        .col_expr(session::Column::UpdatedAt, Expr::value(now))
+        .filter(session::Column::Id.eq(id))
+        .filter(previous)
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error claiming session refill: {}", e)),
+    }
+}
+
+/// Mirrors the user's status onto the session and, when `revoke_tokens` is
+/// set, invalidates every token issued until `now`.
+#[tracing::instrument(skip_all)]
+pub async fn set_status(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    status: UserStatus,
+    suspended_until: Option<DateTime<Utc>>,
+    revoke_tokens: bool,
+    now: DateTime<Utc>,
+) -> Result<Option<session::Model>, String> {
+    let mut update = session::Entity::update_many()
+        .col_expr(session::Column::Status, Expr::value(status))
+        .col_expr(
+            session::Column::SuspendedUntil,
+            Expr::value(suspended_until),
+        )
+        .col_expr(
+            session::Column::Version,
+            Expr::col(session::Column::Version).add(1),
+        )
+        .col_expr(session::Column::UpdatedAt, Expr::value(now));
+    if revoke_tokens {
+        update = update.col_expr(session::Column::TokensRevokedAt, Expr::value(now));
+    }
+    match update
+        .filter(session::Column::UserId.eq(user_id))
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error setting session status: {}", e)),
+    }
+}
@@ -0,0 +1,252 @@
+use crate::entity::{
+    plan,
+    subscription::{self, SubscriptionRenewal, SubscriptionStatus},
+};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::Expr, ActiveModelTrait, ColumnTrait, ConnectionTrait, DatabaseTransaction,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use uuid::Uuid;
+
+#[tracing::instrument(skip_all)]
+pub async fn save(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    plan: &plan::Model,
+    current_period_end: DateTime<Utc>,
+    renewal: SubscriptionRenewal,
+    now: DateTime<Utc>,
+) -> Result<subscription::Model, String> {
+    let new_subscription = subscription::ActiveModel {
+        id: Set(Uuid::new_v4()),
+        user_id: Set(user_id),
+        plan_id: Set(plan.id),
+        status: Set(SubscriptionStatus::Active),
+        started_at: Set(now),
+        current_period_end: Set(current_period_end),
+        cancel_at_period_end: Set(renewal == SubscriptionRenewal::Never),
+        renewal: Set(renewal),
+        created_at: Set(now),
+        updated_at: Set(now),
+    };
+
+    match new_subscription.insert(tx).await {
+        Ok(subscription) => Ok(subscription),
+        Err(e) => Err(format!(
+            "Subscription record was not saved successfully: {}",
+            e
+        )),
+    }
+}
+
+/// The user's most recent subscription still marked active, even if its
+/// period has already run out and the renewal job hasn't caught up yet.
+#[tracing::instrument(skip_all)]
+pub async fn find_current_by_user_id(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+) -> Result<Option<subscription::Model>, String> {
+    match subscription::Entity::find()
+        .filter(subscription::Column::UserId.eq(user_id))
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .order_by_desc(subscription::Column::CurrentPeriodEnd)
+        .one(db)
+        .await
+    {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error finding current subscription: {}", e)),
+    }
+}
+
+/// Whether the user has a subscription that is active and paid up at `now`.
+/// This is what the session's `subscription_status` mirrors.
+#[tracing::instrument(skip_all)]
+pub async fn is_active_by_user_id(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match subscription::Entity::find()
+        .filter(subscription::Column::UserId.eq(user_id))
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .filter(subscription::Column::CurrentPeriodEnd.gt(now))
+        .count(db)
+        .await
+    {
+        Ok(count) => Ok(count > 0),
+        Err(e) => Err(format!("Error checking active subscription: {}", e)),
+    }
+}
+
+/// Whether the user has any subscription row at all, in any state.
+#[tracing::instrument(skip_all)]
+pub async fn exists_by_user_id(db: &impl ConnectionTrait, user_id: i64) -> Result<bool, String> {
+    match subscription::Entity::find()
+        .filter(subscription::Column::UserId.eq(user_id))
+        .count(db)
+        .await
+    {
+        Ok(count) => Ok(count > 0),
+        Err(e) => Err(format!("Error checking subscriptions: {}", e)),
+    }
+}
+
+/// Ends every active subscription the user has with `status`.
+#[tracing::instrument(skip_all)]
+pub async fn end_all_by_user_id(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    status: SubscriptionStatus,
+    now: DateTime<Utc>,
+) -> Result<u64, String> {
+    match subscription::Entity::update_many()
+        .col_expr(subscription::Column::Status, Expr::value(status))
+        .col_expr(subscription::Column::UpdatedAt, Expr::value(now))
+        .filter(subscription::Column::UserId.eq(user_id))
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected),
+        Err(e) => Err(format!("Error ending subscriptions: {}", e)),
+    }
+}
+
+#[tracing::instrument(skip_all)]
+pub async fn set_cancel_at_period_end(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    cancel_at_period_end: bool,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let model = subscription::ActiveModel {
+        id: Set(id),
+        cancel_at_period_end: Set(cancel_at_period_end),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    match model.update(tx).await {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error updating subscription cancellation: {}", e)),
+    }
+}
+
+/// Active subscriptions whose period ended at or before `now`, oldest first.
+#[tracing::instrument(skip_all)]
+pub async fn find_due(
+    db: &impl ConnectionTrait,
+    now: DateTime<Utc>,
+    limit: u64,
+) -> Result<Vec<subscription::Model>, String> {
+    match subscription::Entity::find()
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .filter(subscription::Column::CurrentPeriodEnd.lte(now))
+        .order_by_asc(subscription::Column::CurrentPeriodEnd)
+        .limit(limit)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding due subscriptions: {}", e)),
+    }
+}
+
+/// Moves an active subscription from the period ending at `period_end` to the
+/// next one. Returns `false` if the subscription is no longer in that state,
+/// i.e. someone else already renewed, expired or canceled it.
+#[tracing::instrument(skip_all)]
+pub async fn renew(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    period_end: DateTime<Utc>,
+    next_period_end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match subscription::Entity::update_many()
+        .col_expr(
+            subscription::Column::CurrentPeriodEnd,
+            Expr::value(next_period_end),
+        )
+        .col_expr(subscription::Column::UpdatedAt, Expr::value(now))
+        .filter(subscription::Column::Id.eq(id))
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .filter(subscription::Column::CurrentPeriodEnd.eq(period_end))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error renewing subscription: {}", e)),
+    }
+}
+
+/// Expires an active subscription whose period ended at `period_end`. Returns
+/// `false` if the subscription is no longer in that state.
+#[tracing::instrument(skip_all)]
+pub async fn expire(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    period_end: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match subscription::Entity::update_many()
+        .col_expr(
+            subscription::Column::Status,
+            Expr::value(SubscriptionStatus::Expired),
+        )
+        .col_expr(subscription::Column::UpdatedAt, Expr::value(now))
+        .filter(subscription::Column::Id.eq(id))
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .filter(subscription::Column::CurrentPeriodEnd.eq(period_end))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error expiring subscription: {}", e)),
+    }
+}
+
+/// Pushes an active subscription's period end out to `current_period_end`
+/// and switches it to `renewal`.
+#[tracing::instrument(skip_all)]
+pub async fn extend(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    current_period_end: DateTime<Utc>,
+    renewal: SubscriptionRenewal,
+    now: DateTime<Utc>,
+) -> Result<subscription::Model, String> {
+    let model = subscription::ActiveModel {
+        id: Set(id),
+        current_period_end: Set(current_period_end),
+        cancel_at_period_end: Set(renewal == SubscriptionRenewal::Never),
+        renewal: Set(renewal),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+    match model.update(tx).await {
+        Ok(model) => Ok(model),
+        Err(e) => Err(format!("Error extending subscription: {}", e)),
+    }
+}
+
+/// Ends a single subscription if it is still active.
+#[tracing::instrument(skip_all)]
+pub async fn end(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    status: SubscriptionStatus,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    match subscription::Entity::update_many()
+        .col_expr(subscription::Column::Status, Expr::value(status))
+        .col_expr(subscription::Column::UpdatedAt, Expr::value(now))
+        .filter(subscription::Column::Id.eq(id))
+        .filter(subscription::Column::Status.eq(SubscriptionStatus::Active))
+        .exec(tx)
+        .await
+    {
+        Ok(result) => Ok(result.rows_affected > 0),
+        Err(e) => Err(format!("Error ending subscription: {}", e)),
+    }
+}
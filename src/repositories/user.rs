@@ -1,16 +1,32 @@
-use crate::entity::user;
-use chrono::Utc;
-use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseTransaction, EntityTrait, QueryFilter, Set};
+use crate::entity::user::{self, UserStatus};
+use chrono::{DateTime, Utc};
+use sea_orm::{
+    sea_query::{Expr, Func, LikeExpr},
+    ActiveModelTrait,
+    ActiveValue::NotSet,
+    ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
 use uuid::Uuid;
 
 #[tracing::instrument(skip_all)]
-pub async fn save(tx: &DatabaseTransaction, user_id: i64) -> Result<Uuid, String> {
+pub async fn save(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    username: Option<String>,
+    credits: i64,
+) -> Result<Uuid, String> {
     let new_user = user::ActiveModel {
         id: Set(Uuid::new_v4()),
         user_id: Set(user_id),
-        total_credits: Set(15),
-        credits_remaining: Set(15),
+        username: Set(username),
+        total_credits: Set(credits),
+        credits_remaining: Set(credits),
         subscription_status: Set(false),
+        status: Set(UserStatus::Active),
+        suspended_until: Set(None),
+        status_reason: Set(None),
+        version: Set(1),
         created_at: Set(Utc::now()),
         updated_at: Set(Utc::now()),
     };
@@ -32,12 +48,12 @@ pub async fn find_by_id(tx: &DatabaseTransaction, id: Uuid) -> Result<Option<use
 
 #[tracing::instrument(skip_all)]
 pub async fn find_by_user_id(
-    tx: &DatabaseTransaction,
+    db: &impl ConnectionTrait,
     user_id: i64,
 ) -> Result<Option<user::Model>, String> {
     match user::Entity::find()
         .filter(user::Column::UserId.eq(user_id))
-        .one(tx)
+        .one(db)
         .await
     {
         Ok(model) => Ok(model),
@@ -45,6 +61,124 @@ pub async fn find_by_user_id(
     }
 }
 
+/// Users whose username starts with `prefix`, ignoring case, by username.
+#[tracing::instrument(skip_all)]
+pub async fn find_by_username_prefix(
+    db: &impl ConnectionTrait,
+    prefix: &str,
+    limit: u64,
+) -> Result<Vec<user::Model>, String> {
+    let pattern = format!(
+        "{}%",
+        prefix
+            .to_lowercase()
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_")
+    );
+    match user::Entity::find()
+        .filter(
+            Expr::expr(Func::lower(Expr::col(user::Column::Username)))
+                .like(LikeExpr::new(pattern).escape('\\')),
+        )
+        .order_by_asc(user::Column::Username)
+        .limit(limit)
+        .all(db)
+        .await
+    {
+        Ok(models) => Ok(models),
+        Err(e) => Err(format!("Error finding users by username: {}", e)),
+    }
+}
+
+/// Records the username seen at login when it changed.
+#[tracing::instrument(skip_all)]
+pub async fn set_username(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    username: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    match user::Entity::update_many()
+        .col_expr(user::Column::Username, Expr::value(username))
+        .col_expr(
+            user::Column::Version,
+            Expr::col(user::Column::Version).add(1),
+        )
+        .col_expr(user::Column::UpdatedAt, Expr::value(now))
+        .filter(user::Column::UserId.eq(user_id))
+        .exec(tx)
+        .await
+    {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Error setting username: {}", e)),
+    }
+}
+
+/// Writes `model` only if the row is still at `expected_version`, bumping the
+/// version by one. Returns `None` when another writer got there first.
+#[tracing::instrument(skip_all)]
+pub async fn update_if_version(
+    tx: &DatabaseTransaction,
+    id: Uuid,
+    mut model: user::ActiveModel,
+    expected_version: i64,
+) -> Result<Option<user::Model>, String> {
+    model.id = NotSet;
+    model.version = Set(expected_version + 1);
+    match user::Entity::update_many()
+        .set(model)
+        .filter(user::Column::Id.eq(id))
+        .filter(user::Column::Version.eq(expected_version))
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error updating user with version check: {}", e)),
+    }
+}
+
+/// Adds `credit_delta` to the balance and sets the subscription flag, when
+/// given, in a single statement, bumping the version so that version-checked writers
+/// holding an older copy get a conflict. Grants also count towards `total_credits`.
+#[tracing::instrument(skip_all)]
+pub async fn apply_balance_change(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    credit_delta: i64,
+    subscription_status: Option<bool>,
+    now: DateTime<Utc>,
+) -> Result<Option<user::Model>, String> {
+    let mut update = user::Entity::update_many()
+        .col_expr(
+            user::Column::CreditsRemaining,
+            Expr::col(user::Column::CreditsRemaining).add(credit_delta),
+        )
+        .col_expr(
+            user::Column::TotalCredits,
+            Expr::col(user::Column::TotalCredits).add(credit_delta.max(0)),
+        )
+        .col_expr(
+            user::Column::Version,
+            Expr::col(user::Column::Version).add(1),
+        )
+        .col_expr(user::Column::UpdatedAt, Expr::value(now));
+    if let Some(subscription_status) = subscription_status {
+        update = update.col_expr(
+            user::Column::SubscriptionStatus,
+            Expr::value(subscription_status),
+        );
+    }
+    match update
+        .filter(user::Column::UserId.eq(user_id))
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error applying balance change to user: {}", e)),
+    }
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn exist_by_user_id(tx: &DatabaseTransaction, user_id: i64) -> Result<bool, String> {
     match user::Entity::find()
@@ -57,3 +191,32 @@ pub async fn exist_by_user_id(tx: &DatabaseTransaction, user_id: i64) -> Result<
         Err(e) => Err(format!("Error checking existence by user_id: {}", e)),
     }
 }
+
+/// Sets the user's status, bumping the version so version-checked writers
+/// holding an older copy get a conflict.
+#[tracing::instrument(skip_all)]
+pub async fn set_status(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    status: UserStatus,
+    suspended_until: Option<DateTime<Utc>>,
+    reason: Option<String>,
+    now: DateTime<Utc>,
+) -> Result<Option<user::Model>, String> {
+    match user::Entity::update_many()
+        .col_expr(user::Column::Status, Expr::value(status))
+        .col_expr(user::Column::SuspendedUntil, Expr::value(suspended_until))
+        .col_expr(user::Column::StatusReason, Expr::value(reason))
+        .col_expr(
+            user::Column::Version,
+            Expr::col(user::Column::Version).add(1),
+        )
+        .col_expr(user::Column::UpdatedAt, Expr::value(now))
+        .filter(user::Column::UserId.eq(user_id))
+        .exec_with_returning(tx)
+        .await
+    {
+        Ok(mut models) => Ok(models.pop()),
+        Err(e) => Err(format!("Error setting user status: {}", e)),
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use crate::controllers::admin;
+use crate::utils::admin::verify_admin_key;
+use crate::ServiceState;
+use axum::{
+    middleware,
+    routing::{get, post},
+    Router,
+};
+
+pub fn add_routers(
+    router: axum::Router<Arc<ServiceState>>,
+    state: Arc<ServiceState>,
+) -> axum::Router<Arc<ServiceState>> {
+    let admin_router = Router::new()
+        .route(
+            "/api/admin/payments/:charge_id/refund",
+            post(admin::refund_payment),
+        )
+        .route("/api/admin/promo-codes", post(admin::create_promo_code))
+        .route(
+            "/api/admin/promo-codes/:code/deactivate",
+            post(admin::deactivate_promo_code),
+        )
+        .route("/api/admin/users", get(admin::search_users))
+        .route("/api/admin/users/:user_id", get(admin::get_user))
+        .route(
+            "/api/admin/users/:user_id/credits",
+            post(admin::adjust_credits),
+        )
+        .route(
+            "/api/admin/users/:user_id/cache/expire",
+            post(admin::expire_session_cache),
+        )
+        .route("/api/admin/users/:user_id/ban", post(admin::ban_user))
+        .route("/api/admin/users/:user_id/unban", post(admin::unban_user))
+        .route(
+            "/api/admin/prices",
+            get(admin::list_model_prices).post(admin::create_model_price),
+        )
+        .layer(middleware::from_fn_with_state(state, verify_admin_key));
+    router.merge(admin_router)
+}
@@ -0,0 +1,12 @@
+use std::sync::Arc;
+
+use crate::controllers::metrics;
+use crate::ServiceState;
+use axum::{routing::get, Router};
+
+/// Router for the internal metrics listener, kept apart from the public API.
+pub fn create_router(state: Arc<ServiceState>) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics::get_metrics))
+        .with_state(state)
+}
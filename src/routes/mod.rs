@@ -1,4 +1,11 @@
+pub mod admin;
+pub mod metrics;
+pub mod promo;
+pub mod quota;
+pub mod referral;
 pub mod session;
+pub mod telegram;
+pub mod usage;
 pub mod user;
 use std::sync::Arc;
 
@@ -7,8 +14,14 @@ use axum::Router;
 use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 pub fn create_router(state: Arc<ServiceState>) -> Router {
     let router = Router::new();
-    let router = user::add_routers(router);
+    let router = user::add_routers(router, state.clone());
+    let router = promo::add_routers(router, state.clone());
+    let router = referral::add_routers(router);
     let router = session::add_routers(router, state.clone());
+    let router = telegram::add_routers(router, state.clone());
+    let router = usage::add_routers(router, state.clone());
+    let router = quota::add_routers(router, state.clone());
+    let router = admin::add_routers(router, state.clone());
 
     router.with_state(state).layer(
         TraceLayer::new_for_http().make_span_with(DefaultMakeSpan::default().include_headers(true)),
@@ -0,0 +1,19 @@
+use std::sync::Arc;
+
+use crate::controllers::promo;
+use crate::utils::rate_limit::{rate_limit, RouteGroup};
+use crate::ServiceState;
+use axum::{middleware, routing::post};
+
+pub fn add_routers(
+    router: axum::Router<Arc<ServiceState>>,
+    state: Arc<ServiceState>,
+) -> axum::Router<Arc<ServiceState>> {
+    router.route(
+        "/api/promo/redeem",
+        post(promo::redeem).layer(middleware::from_fn_with_state(
+            RouteGroup::new(state, "promo"),
+            rate_limit,
+        )),
+    )
+}
@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use crate::controllers::quota;
+use crate::utils::secret::verify_signature;
+use crate::ServiceState;
+use axum::{middleware, routing::post};
+
+pub fn add_routers(
+    router: axum::Router<Arc<ServiceState>>,
+    state: Arc<ServiceState>,
+) -> axum::Router<Arc<ServiceState>> {
+    router.route(
+        "/api/quota/consume",
+        post(quota::consume_quota).layer(middleware::from_fn_with_state(state, verify_signature)),
+    )
+}
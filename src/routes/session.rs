@@ -1,6 +1,7 @@
 use std::sync::Arc;
 
 use crate::controllers::session;
+use crate::utils::rate_limit::{rate_limit, RouteGroup};
 use crate::utils::secret::verify_signature;
 use crate::ServiceState;
 use axum::{
@@ -12,8 +13,13 @@ pub fn add_routers(
     router: axum::Router<Arc<ServiceState>>,
     state: Arc<ServiceState>,
 ) -> axum::Router<Arc<ServiceState>> {
+    let limited =
+        middleware::from_fn_with_state(RouteGroup::new(state.clone(), "session"), rate_limit);
     router
-        .route("/api/auth/session", get(session::get_session))
+        .route(
+            "/api/auth/session",
+            get(session::get_session).layer(limited),
+        )
         .route(
             "/api/auth/session",
             post(session::set_session).layer(middleware::from_fn_with_state(
@@ -0,0 +1,16 @@
+use std::sync::Arc;
+
+use crate::controllers::telegram;
+use crate::utils::telegram::verify_webhook_secret;
+use crate::ServiceState;
+use axum::{middleware, routing::post};
+
+pub fn add_routers(
+    router: axum::Router<Arc<ServiceState>>,
+    state: Arc<ServiceState>,
+) -> axum::Router<Arc<ServiceState>> {
+    router.route(
+        "/api/telegram/webhook",
+        post(telegram::webhook).layer(middleware::from_fn_with_state(state, verify_webhook_secret)),
+    )
+}
@@ -1,11 +1,30 @@
 use std::sync::Arc;
 
-use crate::controllers::user;
+use crate::controllers::{session, user};
+use crate::utils::rate_limit::{rate_limit, RouteGroup};
 use crate::ServiceState;
-use axum::routing::post;
+use axum::{
+    middleware,
+    routing::{patch, post},
+    Router,
+};
 
-pub fn add_routers(router: axum::Router<Arc<ServiceState>>) -> axum::Router<Arc<ServiceState>> {
-    router
+pub fn add_routers(
+    router: axum::Router<Arc<ServiceState>>,
+    state: Arc<ServiceState>,
+) -> axum::Router<Arc<ServiceState>> {
+    let auth_router = Router::new()
         .route("/api/auth/login", post(user::login))
         .route("/api/auth/refresh", post(user::refresh))
+        .layer(middleware::from_fn_with_state(
+            RouteGroup::new(state.clone(), "auth"),
+            rate_limit,
+        ));
+    router.merge(auth_router).route(
+        "/api/users/me/preferences",
+        patch(session::patch_preferences).layer(middleware::from_fn_with_state(
+            RouteGroup::new(state, "session"),
+            rate_limit,
+        )),
+    )
 }
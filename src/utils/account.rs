@@ -0,0 +1,42 @@
+use chrono::{DateTime, Utc};
+
+use crate::entity::user::UserStatus;
+
+/// Why the account can't be used at `now`, or `None` if it can. Suspensions
+/// lapse on their own once `suspended_until` has passed.
+pub fn blocked_reason(
+    status: UserStatus,
+    suspended_until: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Option<String> {
+    match (status, suspended_until) {
+        (UserStatus::Active, _) => None,
+        (UserStatus::Suspended, Some(until)) if until <= now => None,
+        (UserStatus::Suspended, Some(until)) => {
+            Some(format!("Account is suspended until {}", until.to_rfc3339()))
+        }
+        (UserStatus::Suspended, None) => Some("Account is suspended".to_string()),
+        (UserStatus::Banned, _) => Some("Account is banned".to_string()),
+    }
+}
+
+/// Whether a token issued at `issued_at_ms` (milliseconds) predates a
+/// revocation. Tokens issued later in the same second stay valid.
+pub fn is_revoked(tokens_revoked_at: Option<DateTime<Utc>>, issued_at_ms: i64) -> bool {
+    tokens_revoked_at.is_some_and(|revoked_at| issued_at_ms < revoked_at.timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn is_revoked_compares_milliseconds() {
+        let revoked_at = Utc.timestamp_millis_opt(1_700_000_000_500).unwrap();
+        assert!(is_revoked(Some(revoked_at), 1_700_000_000_499));
+        assert!(!is_revoked(Some(revoked_at), 1_700_000_000_500));
+        assert!(!is_revoked(Some(revoked_at), 1_700_000_000_900));
+        assert!(!is_revoked(None, 0));
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::utils::secret::constant_time_eq;
+use crate::ServiceState;
+use axum::{
+    extract::{FromRequestParts, Request, State},
+    http::{request::Parts, StatusCode},
+    middleware::Next,
+    response::IntoResponse,
+};
+use tracing::error;
+
+pub const ADMIN_KEY_HEADER: &str = "X-Admin-Key";
+
+/// Checks `X-Admin-Key` against every configured admin key and hands the
+/// matching admin on to the handler as its `AdminActor`.
+pub async fn verify_admin_key(
+    State(state): State<Arc<ServiceState>>,
+    mut req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let api_keys = &state.config.admin.api_keys;
+    if api_keys.is_empty() {
+        let error_message = "Admin API is not configured".to_string();
+        error!("{}", error_message);
+        return Err((StatusCode::SERVICE_UNAVAILABLE, error_message));
+    }
+    let provided = req
+        .headers()
+        .get(ADMIN_KEY_HEADER)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    // Every key is compared so the time taken doesn't reveal which one matched.
+    let actor = api_keys.iter().fold(None, |actor, api_key| {
+        if constant_time_eq(provided, api_key.key.as_bytes()) {
+            Some(AdminActor(api_key.id.clone()))
+        } else {
+            actor
+        }
+    });
+    let Some(actor) = actor else {
+        let error_message = format!("Missing or invalid '{}' header", ADMIN_KEY_HEADER);
+        error!("{}", error_message);
+        return Err((StatusCode::UNAUTHORIZED, error_message));
+    };
+    req.extensions_mut().insert(actor);
+
+    Ok(next.run(req).await)
+}
+
+/// The support agent making an admin request, recorded in the audit log.
+/// Taken from the admin key the request was authenticated with.
+#[derive(Debug, Clone)]
+pub struct AdminActor(pub String);
+
+#[async_trait::async_trait]
+impl FromRequestParts<Arc<ServiceState>> for AdminActor {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &Arc<ServiceState>,
+    ) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AdminActor>()
+            .cloned()
+            .ok_or_else(|| {
+                let error_message = "Admin request was not authenticated".to_string();
+                error!("{}", error_message);
+                (StatusCode::UNAUTHORIZED, error_message)
+            })
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{ConnectionTrait, DatabaseTransaction};
+
+use crate::{
+    config::billing::RefundPolicy,
+    entity::{
+        credit_ledger::LedgerEntryKind,
+        payment, plan, session,
+        subscription::{self, SubscriptionRenewal, SubscriptionStatus},
+    },
+    repositories,
+    utils::credits,
+};
+
+/// Puts the user on `plan` until `period_end` (one plan period from now by
+/// default), renewing as `renewal` says. A current subscription to the same
+/// plan is extended, never shortened; any other active subscription is
+/// canceled and replaced.
+pub async fn start_subscription(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    plan: &plan::Model,
+    period_end: Option<DateTime<Utc>>,
+    renewal: SubscriptionRenewal,
+    now: DateTime<Utc>,
+) -> Result<subscription::Model, String> {
+    if let Some(current) = repositories::subscription::find_current_by_user_id(tx, user_id).await? {
+        if current.plan_id == plan.id {
+            // A running subscription keeps the day of month it started on; a
+            // lapsed one gets a full period from now.
+            let period_end = period_end
+                .unwrap_or_else(|| {
+                    if current.current_period_end > now {
+                        plan.period.next_period_end(
+                            current.started_at,
+                            current.current_period_end,
+                            now,
+                        )
+                    } else {
+                        plan.period.advance(now)
+                    }
+                })
+                .max(current.current_period_end);
+            return repositories::subscription::extend(tx, current.id, period_end, renewal, now)
+                .await;
+        }
+    }
+    repositories::subscription::end_all_by_user_id(tx, user_id, SubscriptionStatus::Canceled, now)
+        .await?;
+    repositories::subscription::save(
+        tx,
+        user_id,
+        plan,
+        period_end.unwrap_or_else(|| plan.period.advance(now)),
+        renewal,
+        now,
+    )
+    .await
+}
+
+/// The user's subscription flag as derived from their subscriptions, or
+/// `None` for users without any. Their stored flag predates subscription
+/// tracking and stays authoritative.
+pub async fn derived_subscription_status(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Option<bool>, String> {
+    if !repositories::subscription::exists_by_user_id(db, user_id).await? {
+        return Ok(None);
+    }
+    repositories::subscription::is_active_by_user_id(db, user_id, now)
+        .await
+        .map(Some)
+}
+
+/// Re-derives the user's subscription flag from their subscriptions and
+/// applies `credit_delta` to the session and user rows. Returns the updated
+/// session for the caller to refresh the cache with.
+pub async fn sync_balance(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    credit_delta: i64,
+    now: DateTime<Utc>,
+) -> Result<Option<session::Model>, String> {
+    let subscription_status = derived_subscription_status(tx, user_id, now).await?;
+    let session = repositories::session::apply_balance_change(
+        tx,
+        user_id,
+        credit_delta,
+        subscription_status,
+        now,
+    )
+    .await?;
+    repositories::user::apply_balance_change(tx, user_id, credit_delta, subscription_status, now)
+        .await?;
+    Ok(session)
+}
+
+/// The effects of a refunded payment that were undone.
+#[derive(Debug, Clone)]
+pub struct PaymentReversal {
+    pub credits_reversed: i64,
+    pub subscription_canceled: bool,
+    pub session: Option<session::Model>,
+}
+
+/// Undoes what `payment` paid for: marks it refunded, takes back its credits
+/// as far as `policy` allows, and ends the subscription it funded unless
+/// another paid payment funds it too. Returns `None` if the payment was
+/// already refunded.
+pub async fn reverse_payment(
+    tx: &DatabaseTransaction,
+    payment: &payment::Model,
+    policy: RefundPolicy,
+    now: DateTime<Utc>,
+) -> Result<Option<PaymentReversal>, String> {
+    if !repositories::payment::mark_refunded(tx, payment.id, now).await? {
+        return Ok(None);
+    }
+
+    // Locked so the clamp is computed against the balance the debit below
+    // actually applies to.
+    let balance = repositories::session::lock_by_user_id(tx, payment.user_id)
+        .await?
+        .map(|session| session.credits_remaining)
+        .unwrap_or_default();
+    let credits_reversed = match policy {
+        RefundPolicy::Clamp => payment.credits_granted.min(balance.max(0)),
+        RefundPolicy::Debt => payment.credits_granted,
+    };
+    if credits_reversed != 0 {
+        repositories::credit_ledger::record(
+            tx,
+            payment.user_id,
+            -credits_reversed,
+            LedgerEntryKind::Refund,
+            format!("refund:{}", payment.telegram_payment_charge_id),
+            now,
+        )
+        .await?;
+    }
+
+    let mut subscription_canceled = false;
+    if let Some(subscription_id) = payment.subscription_id {
+        if repositories::payment::count_paid_by_subscription_id(tx, subscription_id).await? == 0 {
+            subscription_canceled = repositories::subscription::end(
+                tx,
+                subscription_id,
+                SubscriptionStatus::Canceled,
+                now,
+            )
+            .await?;
+        }
+    }
+
+    let session = credits::debit(tx, payment.user_id, credits_reversed, now).await?;
+    Ok(Some(PaymentReversal {
+        credits_reversed,
+        subscription_canceled,
+        session,
+    }))
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Utc};
+use sea_orm::DatabaseTransaction;
+
+use crate::{
+    entity::{
+        credit_bucket::{self, CreditSource},
+        credit_ledger::LedgerEntryKind,
+        session,
+    },
+    repositories,
+    utils::billing,
+};
+
+/// Puts `amount` credits into a new bucket without touching the balance
+/// columns; callers that don't write the balance themselves use `grant`.
+pub async fn add_bucket(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    source: CreditSource,
+    expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if amount > 0 {
+        repositories::credit_bucket::save(tx, user_id, amount, source, expires_at, now).await?;
+    }
+    Ok(())
+}
+
+/// Draws `amount` credits from the user's buckets, soonest-expiring first,
+/// without touching the balance columns. Anything the buckets can't cover
+/// comes out of the balance that isn't tracked in buckets: credits from before
+/// buckets existed, or debt.
+pub async fn consume(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let mut outstanding = amount;
+    for bucket in repositories::credit_bucket::lock_spendable_by_user_id(tx, user_id, now).await? {
+        if outstanding <= 0 {
+            break;
+        }
+        let taken = bucket.remaining.min(outstanding);
+        repositories::credit_bucket::set_remaining(tx, bucket.id, bucket.remaining - taken, now)
+            .await?;
+        outstanding -= taken;
+    }
+    Ok(())
+}
+
+/// Adds a bucket and raises the balance by the same amount.
+pub async fn grant(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    source: CreditSource,
+    expires_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> Result<Option<session::Model>, String> {
+    add_bucket(tx, user_id, amount, source, expires_at, now).await?;
+    billing::sync_balance(tx, user_id, amount, now).await
+}
+
+/// Consumes buckets and lowers the balance by the same amount.
+pub async fn debit(
+    tx: &DatabaseTransaction,
+    user_id: i64,
+    amount: i64,
+    now: DateTime<Utc>,
+) -> Result<Option<session::Model>, String> {
+    consume(tx, user_id, amount, now).await?;
+    billing::sync_balance(tx, user_id, -amount, now).await
+}
+
+/// Removes what is left in an expired bucket from the balance and returns the
+/// amount removed with the updated session. Does nothing if the bucket changed
+/// since it was read.
+///
+/// Direct balance writes don't draw from buckets, so a bucket can hold more
+/// than the balance still has; only what the balance has is taken away.
+pub async fn expire(
+    tx: &DatabaseTransaction,
+    bucket: &credit_bucket::Model,
+    now: DateTime<Utc>,
+) -> Result<(i64, Option<session::Model>), String> {
+    if !repositories::credit_bucket::expire(tx, bucket.id, bucket.remaining, now).await? {
+        return Ok((0, None));
+    }
+    let balance = repositories::session::lock_by_user_id(tx, bucket.user_id)
+        .await?
+        .map_or(0, |session| session.credits_remaining);
+    let expired = bucket.remaining.min(balance.max(0));
+    if expired > 0 {
+        repositories::credit_ledger::record(
+            tx,
+            bucket.user_id,
+            -expired,
+            LedgerEntryKind::Expiry,
+            format!("expiry:{}", bucket.id),
+            now,
+        )
+        .await?;
+    }
+    let session = billing::sync_balance(tx, bucket.user_id, -expired, now).await?;
+    Ok((expired, session))
+}
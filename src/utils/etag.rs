@@ -0,0 +1,51 @@
+use axum::http::{header, HeaderMap, StatusCode};
+use tracing::error;
+
+/// Strong entity tag for a row version.
+pub fn etag(version: i64) -> String {
+    format!("\"{version}\"")
+}
+
+/// Reads the expected row version from an `If-Match` header.
+///
+/// Accepts a single strong or weak tag holding the version number; `*` and a
+/// missing header both mean "any version".
+pub fn parse_if_match(headers: &HeaderMap) -> Result<Option<i64>, (StatusCode, String)> {
+    let Some(value) = headers.get(header::IF_MATCH) else {
+        return Ok(None);
+    };
+    let invalid = || {
+        let error_message = "Malformed 'If-Match' header: expected a version tag".to_string();
+        error!("{}", error_message);
+        (StatusCode::BAD_REQUEST, error_message)
+    };
+    let value = value.to_str().map_err(|_| invalid())?.trim();
+    if value == "*" {
+        return Ok(None);
+    }
+    value
+        .trim_start_matches("W/")
+        .trim_matches('"')
+        .parse::<i64>()
+        .map(Some)
+        .map_err(|_| invalid())
+}
+
+/// Combines an `If-Match` header with an `expected_version` body field,
+/// rejecting requests where the two disagree.
+pub fn expected_version(
+    headers: &HeaderMap,
+    body_version: Option<i64>,
+) -> Result<Option<i64>, (StatusCode, String)> {
+    match (parse_if_match(headers)?, body_version) {
+        (Some(header), Some(body)) if header != body => {
+            let error_message = format!(
+                "'If-Match' version {} does not match expected_version {}",
+                header, body
+            );
+            error!("{}", error_message);
+            Err((StatusCode::BAD_REQUEST, error_message))
+        }
+        (header, body) => Ok(header.or(body)),
+    }
+}
@@ -36,6 +36,23 @@ pub fn get_user_id(init_data: &str) -> i64 {
     0
 }
 
+/// The user's Telegram username, if they have one.
+pub fn get_username(init_data: &str) -> Option<String> {
+    form_urlencoded::parse(init_data.as_bytes())
+        .find(|(key, _)| key == "user")
+        .and_then(|(_, user)| serde_json::from_str::<Value>(&user).ok())
+        .and_then(|user| user.get("username")?.as_str().map(str::to_string))
+        .filter(|username| !username.is_empty())
+}
+
+/// The `start_param` the mini app was opened with, if any.
+pub fn get_start_param(init_data: &str) -> Option<String> {
+    form_urlencoded::parse(init_data.as_bytes())
+        .find(|(key, _)| key == "start_param")
+        .map(|(_, value)| value.into_owned())
+        .filter(|value| !value.is_empty())
+}
+
 pub fn validate_initdata(init_data: &str, bot_token: &str) -> bool {
     if init_data.contains("query_id") && init_data.contains("hash=") {
         let parsed_data: HashMap<_, _> = form_urlencoded::parse(init_data.as_bytes())
@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use tracing::{info, warn};
+
+use crate::{
+    client::{
+        cache::{CacheBackend, CacheClient},
+        memory::MemoryCache,
+    },
+    utils::session::RedisKey,
+};
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_millis(100);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(10);
+
+/// Keeps the per-instance session cache coherent across replicas.
+///
+/// Writers publish the keys they changed on a shared channel and every
+/// instance evicts them from its local cache. While the subscription is down
+/// messages can be missed, so the local cache is flushed whenever it is
+/// (re-)established.
+///
+/// Every eviction bumps a generation counter before it touches the local
+/// cache, so a fill that read its value before an eviction can tell that it
+/// may be stale and drop what it wrote.
+pub struct InvalidationBus {
+    cache: Arc<CacheClient>,
+    local: Arc<MemoryCache>,
+    channel: String,
+    generation: AtomicU64,
+}
+
+impl InvalidationBus {
+    pub fn new(cache: Arc<CacheClient>, channel: String) -> Arc<Self> {
+        let bus = Arc::new(Self {
+            cache,
+            local: MemoryCache::new(),
+            channel,
+            generation: AtomicU64::new(0),
+        });
+        tokio::spawn(bus.clone().listen());
+        bus
+    }
+
+    /// The in-process cache kept coherent by this bus.
+    pub fn local(&self) -> &CacheClient {
+        self.local.as_ref()
+    }
+
+    /// Read before fetching a value to cache locally and pass to
+    /// `is_current` once it is written.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Whether nothing was evicted since `generation` was read.
+    pub fn is_current(&self, generation: u64) -> bool {
+        self.generation() == generation
+    }
+
+    /// Evicts `key` from this instance's local cache and tells every other
+    /// instance to do the same.
+    pub async fn invalidate(&self, key: &impl RedisKey) -> Result<(), String> {
+        let key = key.to_string();
+        self.evict(&key).await?;
+        self.cache
+            .publish(&self.channel, &key)
+            .await
+            .map_err(|e| format!("Failed to publish invalidation for {}: {}", key, e))
+    }
+
+    async fn listen(self: Arc<Self>) {
+        let mut delay = MIN_RECONNECT_DELAY;
+        loop {
+            match self.cache.subscribe(&self.channel).await {
+                Ok(mut messages) => {
+                    self.clear();
+                    delay = MIN_RECONNECT_DELAY;
+                    info!("Listening for cache invalidations on {}", self.channel);
+                    while let Some(key) = messages.next().await {
+                        metrics::counter!("cache_invalidations_received_total").increment(1);
+                        if let Err(e) = self.evict(&key).await {
+                            warn!("Failed to evict {} from the local cache: {}", key, e);
+                        }
+                    }
+                    warn!(
+                        "Cache invalidation subscription on {} was lost",
+                        self.channel
+                    );
+                }
+                Err(e) => {
+                    warn!("Failed to subscribe to {}: {}", self.channel, e);
+                }
+            }
+            metrics::counter!("cache_invalidation_resubscribes_total").increment(1);
+            self.clear();
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    async fn evict(&self, key: &str) -> Result<bool, String> {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.local.del(key).await
+    }
+
+    fn clear(&self) {
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.local.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::session::SessionKey;
+
+    #[tokio::test]
+    async fn invalidate_moves_the_generation_on() {
+        let bus = InvalidationBus::new(MemoryCache::new(), "invalidations".to_string());
+        let generation = bus.generation();
+        assert!(bus.is_current(generation));
+
+        bus.invalidate(&SessionKey { user_id: 1 }).await.unwrap();
+        assert!(!bus.is_current(generation));
+    }
+}
@@ -0,0 +1,18 @@
+use sea_orm::{ConnectionTrait, DatabaseTransaction, DbBackend, Statement};
+
+/// Tries to take a Postgres advisory lock that is released when `tx` ends.
+/// Returns `false` immediately if another session holds it.
+pub async fn try_advisory_xact_lock(tx: &DatabaseTransaction, key: i64) -> Result<bool, String> {
+    let statement = Statement::from_sql_and_values(
+        DbBackend::Postgres,
+        "SELECT pg_try_advisory_xact_lock($1) AS locked",
+        [key.into()],
+    );
+    let row = tx
+        .query_one(statement)
+        .await
+        .map_err(|e| format!("Error taking advisory lock {}: {}", key, e))?
+        .ok_or_else(|| format!("Advisory lock {} query returned no row", key))?;
+    row.try_get::<bool>("", "locked")
+        .map_err(|e| format!("Error reading advisory lock {} result: {}", key, e))
+}
@@ -0,0 +1,166 @@
+use axum::http::{header, HeaderMap, StatusCode};
+use serde_json::Value;
+use tracing::error;
+
+pub const CONTENT_TYPE: &str = "application/merge-patch+json";
+
+/// Applies an RFC 7396 JSON Merge Patch to `target` in place.
+///
+/// Object members in `patch` are merged recursively, `null` members remove the
+/// key, and any non-object patch replaces `target` outright.
+pub fn apply(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            apply(target.entry(key.as_str()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+/// Parses a merge-patch request body. Accepts `application/merge-patch+json`
+/// as well as plain `application/json`, which clients often send instead.
+pub fn parse_body(headers: &HeaderMap, body: &[u8]) -> Result<Value, (StatusCode, String)> {
+    let media_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_ascii_lowercase());
+    if !matches!(
+        media_type.as_deref(),
+        Some(CONTENT_TYPE | "application/json")
+    ) {
+        let error_message = format!("Expected a '{}' request body", CONTENT_TYPE);
+        error!("{}", error_message);
+        return Err((StatusCode::UNSUPPORTED_MEDIA_TYPE, error_message));
+    }
+    serde_json::from_slice(body).map_err(|e| {
+        let error_message = format!("Malformed merge patch: {}", e);
+        error!("{}", error_message);
+        (StatusCode::BAD_REQUEST, error_message)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+    use serde_json::json;
+
+    fn merged(target: Value, patch: Value) -> Value {
+        let mut target = target;
+        apply(&mut target, &patch);
+        target
+    }
+
+    #[test]
+    fn replaces_and_adds_members() {
+        assert_eq!(
+            merged(json!({"a": "b"}), json!({"a": "c"})),
+            json!({"a": "c"})
+        );
+        assert_eq!(
+            merged(json!({"a": "b"}), json!({"b": "c"})),
+            json!({"a": "b", "b": "c"})
+        );
+    }
+
+    #[test]
+    fn null_removes_members() {
+        assert_eq!(merged(json!({"a": "b"}), json!({"a": null})), json!({}));
+        assert_eq!(
+            merged(json!({"a": "b", "b": "c"}), json!({"a": null})),
+            json!({"b": "c"})
+        );
+        assert_eq!(
+            merged(json!({"a": "b"}), json!({"x": null})),
+            json!({"a": "b"})
+        );
+    }
+
+    #[test]
+    fn merges_nested_objects() {
+        assert_eq!(
+            merged(
+                json!({"a": {"b": "c", "d": "e"}, "f": 1}),
+                json!({"a": {"b": "x", "d": null}})
+            ),
+            json!({"a": {"b": "x"}, "f": 1})
+        );
+        assert_eq!(
+            merged(json!({"e": null}), json!({"a": 1})),
+            json!({"e": null, "a": 1})
+        );
+        assert_eq!(
+            merged(json!({}), json!({"a": {"bb": {"ccc": null}}})),
+            json!({"a": {"bb": {}}})
+        );
+    }
+
+    #[test]
+    fn non_objects_replace_the_target() {
+        assert_eq!(
+            merged(json!({"a": ["b"]}), json!({"a": "c"})),
+            json!({"a": "c"})
+        );
+        assert_eq!(
+            merged(json!({"a": "c"}), json!({"a": ["b"]})),
+            json!({"a": ["b"]})
+        );
+        assert_eq!(
+            merged(json!({"a": [{"b": "c"}]}), json!({"a": [1]})),
+            json!({"a": [1]})
+        );
+        assert_eq!(
+            merged(json!(["a", "b"]), json!(["c", "d"])),
+            json!(["c", "d"])
+        );
+        assert_eq!(merged(json!({"a": "b"}), json!(["c"])), json!(["c"]));
+        assert_eq!(merged(json!({"a": "foo"}), json!(null)), json!(null));
+        assert_eq!(merged(json!({"a": "foo"}), json!("bar")), json!("bar"));
+        assert_eq!(
+            merged(json!(["a", "b"]), json!({"a": "b"})),
+            json!({"a": "b"})
+        );
+    }
+
+    fn headers(content_type: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+        headers
+    }
+
+    #[test]
+    fn parse_body_accepts_merge_patch_and_json() {
+        let body = br#"{"theme": null}"#;
+        for content_type in [
+            "application/merge-patch+json",
+            "application/merge-patch+json; charset=utf-8",
+            "application/json",
+        ] {
+            assert_eq!(
+                parse_body(&headers(content_type), body).unwrap(),
+                json!({"theme": null})
+            );
+        }
+    }
+
+    #[test]
+    fn parse_body_rejects_other_media_types() {
+        let body = br#"{}"#;
+        let (status, _) = parse_body(&headers("text/plain"), body).unwrap_err();
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let (status, _) = parse_body(&HeaderMap::new(), body).unwrap_err();
+        assert_eq!(status, StatusCode::UNSUPPORTED_MEDIA_TYPE);
+        let (status, _) = parse_body(&headers(CONTENT_TYPE), b"{").unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -1,4 +1,21 @@
+pub mod account;
+pub mod admin;
+pub mod billing;
+pub mod credits;
+pub mod etag;
 pub mod initdata;
+pub mod invalidation;
 pub mod jwt;
+pub mod lock;
+pub mod merge_patch;
+pub mod pricing;
+pub mod quota;
+pub mod rate_limit;
+pub mod referral;
+pub mod refill;
 pub mod secret;
 pub mod session;
+pub mod signup;
+pub mod singleflight;
+pub mod telegram;
+pub mod transaction;
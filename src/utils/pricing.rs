@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+
+use crate::entity::model_price::{self, PriceUnit};
+
+/// Units of work one model call consumed.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Usage {
+    pub requests: i64,
+    pub input_tokens: i64,
+    pub output_tokens: i64,
+    pub images: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChargeLine {
+    pub unit: PriceUnit,
+    pub quantity: i64,
+    pub credits: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Charge {
+    pub credits: i64,
+    pub lines: Vec<ChargeLine>,
+}
+
+/// Prices `usage` against `prices`, which must be the model's effective
+/// prices newest first, as returned by `find_effective_by_model`. Each line
+/// is rounded up to a whole credit. Fails if the model has no price at all or
+/// the usage includes a unit the model isn't priced for.
+pub fn quote(model: &str, prices: &[model_price::Model], usage: Usage) -> Result<Charge, String> {
+    if prices.is_empty() {
+        return Err(format!("No price is set for model '{}'", model));
+    }
+
+    let mut lines = Vec::new();
+    for (unit, quantity) in [
+        (PriceUnit::Request, usage.requests),
+        (PriceUnit::InputToken, usage.input_tokens),
+        (PriceUnit::OutputToken, usage.output_tokens),
+        (PriceUnit::Image, usage.images),
+    ] {
+        if quantity == 0 {
+            continue;
+        }
+        let price = prices
+            .iter()
+            .find(|price| price.unit == unit)
+            .ok_or_else(|| format!("Model '{}' has no price for {:?} units", model, unit))?;
+        let per_units = price.per_units as i128;
+        let credits = ((quantity as i128 * price.credits as i128 + per_units - 1) / per_units)
+            .try_into()
+            .map_err(|_| format!("Charge for {} {:?} units is out of range", quantity, unit))?;
+        lines.push(ChargeLine {
+            unit,
+            quantity,
+            credits,
+        });
+    }
+
+    Ok(Charge {
+        credits: lines.iter().map(|line| line.credits).sum(),
+        lines,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn price(
+        unit: PriceUnit,
+        credits: i64,
+        per_units: i64,
+        effective_from: DateTime<Utc>,
+    ) -> model_price::Model {
+        model_price::Model {
+            id: Uuid::new_v4(),
+            model: "test-model".to_string(),
+            unit,
+            credits,
+            per_units,
+            effective_from,
+            created_by: "test".to_string(),
+            created_at: effective_from,
+        }
+    }
+
+    fn day(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, day, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn quote_rounds_each_line_up() {
+        let prices = [
+            price(PriceUnit::InputToken, 3, 1000, day(1)),
+            price(PriceUnit::OutputToken, 5, 1000, day(1)),
+        ];
+        let usage = Usage {
+            input_tokens: 1001,
+            output_tokens: 1000,
+            ..Default::default()
+        };
+        let charge = quote("test-model", &prices, usage).unwrap();
+        assert_eq!(charge.lines[0].credits, 4);
+        assert_eq!(charge.lines[1].credits, 5);
+        assert_eq!(charge.credits, 9);
+    }
+
+    #[test]
+    fn quote_applies_per_units() {
+        let prices = [
+            price(PriceUnit::Request, 2, 1, day(1)),
+            price(PriceUnit::InputToken, 7, 1_000_000, day(1)),
+        ];
+        let usage = Usage {
+            requests: 3,
+            input_tokens: 2_000_000,
+            ..Default::default()
+        };
+        let charge = quote("test-model", &prices, usage).unwrap();
+        assert_eq!(charge.lines[0].credits, 6);
+        assert_eq!(charge.lines[1].credits, 14);
+        assert_eq!(charge.credits, 20);
+    }
+
+    #[test]
+    fn quote_uses_newest_effective_price() {
+        let prices = [
+            price(PriceUnit::Request, 10, 1, day(5)),
+            price(PriceUnit::Request, 1, 1, day(1)),
+        ];
+        let usage = Usage {
+            requests: 2,
+            ..Default::default()
+        };
+        assert_eq!(quote("test-model", &prices, usage).unwrap().credits, 20);
+    }
+
+    #[test]
+    fn quote_skips_zero_quantities_and_rejects_unpriced_units() {
+        let prices = [price(PriceUnit::Request, 1, 1, day(1))];
+        let charge = quote("test-model", &prices, Usage::default()).unwrap();
+        assert!(charge.lines.is_empty());
+        assert_eq!(charge.credits, 0);
+
+        let usage = Usage {
+            images: 1,
+            ..Default::default()
+        };
+        assert!(quote("test-model", &prices, usage).is_err());
+        assert!(quote("test-model", &[], Usage::default()).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use sea_orm::ConnectionTrait;
+use serde::Serialize;
+
+use crate::{
+    client::cache::CacheClient,
+    config::quota::{QuotaConfig, QuotaDefinition, FREE_TIER},
+    repositories,
+};
+
+/// Where a quota stands for one user.
+#[derive(Debug, Clone, Serialize)]
+pub struct QuotaState {
+    pub quota: String,
+    /// `None` when the user's tier is unlimited.
+    pub limit: Option<u64>,
+    pub used: u64,
+    pub remaining: Option<u64>,
+    /// When the oldest use in the window drops out, freeing capacity again.
+    pub reset_at: Option<DateTime<Utc>>,
+    /// Whether the units asked for were granted; always `true` for reads.
+    pub allowed: bool,
+}
+
+/// The plan tier whose limits apply to the user.
+pub async fn tier_for(
+    db: &impl ConnectionTrait,
+    user_id: i64,
+    subscription_status: bool,
+) -> Result<String, String> {
+    if !subscription_status {
+        return Ok(FREE_TIER.to_string());
+    }
+    let Some(subscription) =
+        repositories::subscription::find_current_by_user_id(db, user_id).await?
+    else {
+        return Ok(FREE_TIER.to_string());
+    };
+    Ok(repositories::plan::find_by_id(db, subscription.plan_id)
+        .await?
+        .map(|plan| plan.tier)
+        .unwrap_or_else(|| FREE_TIER.to_string()))
+}
+
+fn key(quota: &str, user_id: i64) -> String {
+    format!("QUOTA_{}_{{{}}}", quota, user_id)
+}
+
+/// Takes `cost` units of `quota` if the user's tier has room for them. A cost
+/// of zero only reports the current state.
+pub async fn consume(
+    cache: &CacheClient,
+    quota: &str,
+    definition: &QuotaDefinition,
+    tier: &str,
+    user_id: i64,
+    cost: u64,
+    now: DateTime<Utc>,
+) -> Result<QuotaState, String> {
+    let Some(&limit) = definition.limits.get(tier) else {
+        return Ok(QuotaState {
+            quota: quota.to_string(),
+            limit: None,
+            used: 0,
+            remaining: None,
+            reset_at: None,
+            allowed: true,
+        });
+    };
+
+    let window = cache
+        .window_consume(
+            &key(quota, user_id),
+            limit,
+            definition.window,
+            cost,
+            now.timestamp_millis(),
+        )
+        .await?;
+    let window_length = TimeDelta::from_std(definition.window)
+        .map_err(|e| format!("Quota window is out of range: {}", e))?;
+    Ok(QuotaState {
+        quota: quota.to_string(),
+        limit: Some(limit),
+        used: window.used,
+        remaining: Some(limit.saturating_sub(window.used)),
+        reset_at: window
+            .oldest_ms
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+            .map(|oldest| oldest + window_length),
+        allowed: window.allowed,
+    })
+}
+
+/// The state of every configured quota for the user.
+pub async fn current(
+    cache: &CacheClient,
+    config: &QuotaConfig,
+    tier: &str,
+    user_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<QuotaState>, String> {
+    let mut states = Vec::with_capacity(config.quotas.len());
+    for (quota, definition) in &config.quotas {
+        states.push(consume(cache, quota, definition, tier, user_id, 0, now).await?);
+    }
+    Ok(states)
+}
@@ -0,0 +1,211 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use tracing::{info, warn};
+
+use crate::{config::rate_limit::RateLimitConfig, utils::jwt::UserClaims, ServiceState};
+
+/// A group of routes sharing one set of limits, e.g. `auth` or `session`.
+#[derive(Clone)]
+pub struct RouteGroup {
+    pub state: Arc<ServiceState>,
+    pub name: &'static str,
+}
+
+impl RouteGroup {
+    pub fn new(state: Arc<ServiceState>, name: &'static str) -> Self {
+        Self { state, name }
+    }
+}
+
+/// Token bucket rate limiting per client. Requests with a valid access token
+/// are counted against the user, everything else against the client address.
+/// If the cache can't be reached the request is let through.
+pub async fn rate_limit(State(group): State<RouteGroup>, req: Request, next: Next) -> Response {
+    let config = &group.state.config.rate_limit;
+    let Some(limit) = config.groups.get(group.name).copied() else {
+        return next.run(req).await;
+    };
+
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let client = match user_id(&group.state, req.headers()) {
+        Some(uid) => format!("uid:{}", uid),
+        None => match client_ip(config, peer, req.headers()) {
+            Some(ip) => format!("ip:{}", ip),
+            None => "ip:unknown".to_string(),
+        },
+    };
+
+    let bucket = match group
+        .state
+        .cache
+        .token_bucket(
+            &format!("RATE_LIMIT_{}_{{{}}}", group.name, client),
+            limit.burst,
+            limit.per_minute as f64 / 60_000.0,
+            1,
+            Utc::now().timestamp_millis(),
+        )
+        .await
+    {
+        Ok(bucket) => bucket,
+        Err(e) => {
+            warn!("Rate limiting skipped for {}: {}", client, e);
+            return next.run(req).await;
+        }
+    };
+
+    let mut response = if bucket.allowed {
+        next.run(req).await
+    } else {
+        metrics::counter!("rate_limited_requests_total", "group" => group.name).increment(1);
+        info!("Rate limited {} on route group {}", client, group.name);
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many requests".to_string(),
+        )
+            .into_response();
+        response.headers_mut().insert(
+            header::RETRY_AFTER,
+            HeaderValue::from(bucket.retry_after_ms.div_ceil(1000).max(1)),
+        );
+        response
+    };
+    let headers = response.headers_mut();
+    headers.insert("RateLimit-Limit", HeaderValue::from(limit.burst));
+    headers.insert("RateLimit-Remaining", HeaderValue::from(bucket.remaining));
+    headers.insert(
+        "RateLimit-Reset",
+        HeaderValue::from(bucket.reset_ms.div_ceil(1000)),
+    );
+    response
+}
+
+fn user_id(state: &ServiceState, headers: &HeaderMap) -> Option<i64> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    UserClaims::decode(token, &state.config.jwt.access_token_secret)
+        .ok()
+        .map(|data| data.claims.uid)
+}
+
+/// The address of the client behind any trusted proxies: the forwarding
+/// header is read right to left, skipping hops that are themselves trusted,
+/// and only believed when the connection comes from a trusted proxy.
+fn client_ip(
+    config: &RateLimitConfig,
+    peer: Option<IpAddr>,
+    headers: &HeaderMap,
+) -> Option<IpAddr> {
+    let peer = peer?.to_canonical();
+    if !config.is_trusted_proxy(peer) {
+        return Some(peer);
+    }
+    let forwarded: Vec<IpAddr> = headers
+        .get_all(config.client_ip_header.as_str())
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|hop| hop.trim().parse::<IpAddr>().ok())
+        .map(|hop| hop.to_canonical())
+        .collect();
+    forwarded
+        .iter()
+        .rev()
+        .find(|ip| !config.is_trusted_proxy(**ip))
+        .or(forwarded.first())
+        .copied()
+        .or(Some(peer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(trusted_proxies: &[&str]) -> RateLimitConfig {
+        RateLimitConfig {
+            trusted_proxies: trusted_proxies
+                .iter()
+                .map(|proxy| proxy.parse().unwrap())
+                .collect(),
+            client_ip_header: "X-Forwarded-For".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn forwarded(values: &[&str]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for value in values {
+            headers.append("X-Forwarded-For", HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    fn ip(s: &str) -> Option<IpAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn client_ip_ignores_header_from_untrusted_peer() {
+        let config = config(&["10.0.0.0/8"]);
+        let headers = forwarded(&["203.0.113.9"]);
+        assert_eq!(
+            client_ip(&config, ip("198.51.100.1"), &headers),
+            ip("198.51.100.1")
+        );
+    }
+
+    #[test]
+    fn client_ip_walks_trusted_hops_right_to_left() {
+        let config = config(&["10.0.0.0/8"]);
+        // The left-most entry is whatever the client claimed; the first
+        // untrusted hop from the right is the real client.
+        let headers = forwarded(&["1.1.1.1, 203.0.113.9, 10.0.0.2"]);
+        assert_eq!(
+            client_ip(&config, ip("10.0.0.1"), &headers),
+            ip("203.0.113.9")
+        );
+
+        // Repeated headers form one chain, in order.
+        let headers = forwarded(&["1.1.1.1", "203.0.113.9, 10.0.0.2"]);
+        assert_eq!(
+            client_ip(&config, ip("10.0.0.1"), &headers),
+            ip("203.0.113.9")
+        );
+    }
+
+    #[test]
+    fn client_ip_falls_back_when_every_hop_is_trusted() {
+        let config = config(&["10.0.0.0/8"]);
+        let headers = forwarded(&["10.0.0.5, 10.0.0.2"]);
+        assert_eq!(client_ip(&config, ip("10.0.0.1"), &headers), ip("10.0.0.5"));
+        assert_eq!(
+            client_ip(&config, ip("10.0.0.1"), &HeaderMap::new()),
+            ip("10.0.0.1")
+        );
+        assert_eq!(client_ip(&config, None, &headers), None);
+    }
+
+    #[test]
+    fn client_ip_skips_unparsable_hops_and_maps_ipv4() {
+        let config = config(&["10.0.0.0/8"]);
+        let headers = forwarded(&["203.0.113.9, unknown, ::ffff:10.0.0.2"]);
+        assert_eq!(
+            client_ip(&config, ip("::ffff:10.0.0.1"), &headers),
+            ip("203.0.113.9")
+        );
+    }
+}
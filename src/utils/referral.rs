@@ -0,0 +1,174 @@
+use chrono::{DateTime, Utc};
+use sea_orm::{DatabaseTransaction, Set};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::{
+    config::{billing::BillingConfig, referral::ReferralConfig},
+    entity::{
+        credit_bucket::CreditSource,
+        credit_ledger::LedgerEntryKind,
+        referral::{self, ReferralStatus},
+        session,
+    },
+    repositories,
+    utils::credits,
+};
+
+const CODE_PREFIX: &str = "ref_";
+
+/// A user's referral code: their user ID in base 36, so codes stay short and
+/// need no lookup table.
+pub fn code_for(user_id: i64) -> String {
+    let mut id = user_id.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        digits.push(std::char::from_digit((id % 36) as u32, 36).unwrap());
+        id /= 36;
+        if id == 0 {
+            break;
+        }
+    }
+    format!("{CODE_PREFIX}{}", digits.iter().rev().collect::<String>())
+}
+
+/// The referrer's user ID encoded in a `start_param`, if it is a referral code.
+pub fn referrer_from_code(code: &str) -> Option<i64> {
+    let digits = code.strip_prefix(CODE_PREFIX)?;
+    i64::from_str_radix(digits, 36).ok().filter(|id| *id > 0)
+}
+
+pub fn invite_link(config: &ReferralConfig, user_id: i64) -> String {
+    format!("{}{}", config.link_base, code_for(user_id))
+}
+
+/// Records that `invitee_user_id`, who is logging in for the first time, was
+/// invited with `code`, rewarding both sides right away unless rewards wait
+/// for a purchase. Unknown codes and self-referrals are ignored. Returns the
+/// sessions whose balances changed.
+pub async fn record_signup(
+    tx: &DatabaseTransaction,
+    config: &ReferralConfig,
+    billing: &BillingConfig,
+    invitee_user_id: i64,
+    code: &str,
+    now: DateTime<Utc>,
+) -> Result<Vec<session::Model>, String> {
+    let Some(referrer_user_id) = referrer_from_code(code) else {
+        info!("Ignoring start_param that is not a referral code: {}", code);
+        return Ok(Vec::new());
+    };
+    if referrer_user_id == invitee_user_id {
+        warn!("User ID {} tried to refer themselves", invitee_user_id);
+        return Ok(Vec::new());
+    }
+    if !repositories::user::exist_by_user_id(tx, referrer_user_id).await? {
+        warn!(
+            "Referral code {} names unknown user ID {}",
+            code, referrer_user_id
+        );
+        return Ok(Vec::new());
+    }
+    // Only first logins get here, so the invitee can't have invited anyone
+    // yet; this guards against loops should that ever change.
+    if repositories::referral::find_by_invitee(tx, referrer_user_id)
+        .await?
+        .is_some_and(|referral| referral.referrer_user_id == invitee_user_id)
+    {
+        warn!(
+            "Ignoring referral loop between user IDs {} and {}",
+            referrer_user_id, invitee_user_id
+        );
+        return Ok(Vec::new());
+    }
+
+    let referral = repositories::referral::save(
+        tx,
+        referral::ActiveModel {
+            id: Set(Uuid::new_v4()),
+            referrer_user_id: Set(referrer_user_id),
+            invitee_user_id: Set(invitee_user_id),
+            status: Set(ReferralStatus::Pending),
+            referrer_credits: Set(0),
+            invitee_credits: Set(0),
+            created_at: Set(now),
+            rewarded_at: Set(None),
+        },
+    )
+    .await?;
+    info!(
+        "User ID {} was referred by user ID {}",
+        invitee_user_id, referrer_user_id
+    );
+
+    if config.require_purchase {
+        return Ok(Vec::new());
+    }
+    reward(tx, config, billing, &referral, now).await
+}
+
+/// Pays out a pending referral once its invitee makes their first purchase.
+pub async fn reward_after_purchase(
+    tx: &DatabaseTransaction,
+    config: &ReferralConfig,
+    billing: &BillingConfig,
+    invitee_user_id: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<session::Model>, String> {
+    match repositories::referral::find_pending_by_invitee(tx, invitee_user_id).await? {
+        Some(referral) => reward(tx, config, billing, &referral, now).await,
+        None => Ok(Vec::new()),
+    }
+}
+
+async fn reward(
+    tx: &DatabaseTransaction,
+    config: &ReferralConfig,
+    billing: &BillingConfig,
+    referral: &referral::Model,
+    now: DateTime<Utc>,
+) -> Result<Vec<session::Model>, String> {
+    if !repositories::referral::mark_rewarded(
+        tx,
+        referral.id,
+        config.referrer_bonus,
+        config.invitee_bonus,
+        now,
+    )
+    .await?
+    {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for (user_id, bonus, side) in [
+        (referral.referrer_user_id, config.referrer_bonus, "referrer"),
+        (referral.invitee_user_id, config.invitee_bonus, "invitee"),
+    ] {
+        if bonus == 0 {
+            continue;
+        }
+        repositories::credit_ledger::record(
+            tx,
+            user_id,
+            bonus,
+            LedgerEntryKind::Referral,
+            format!("referral:{}:{}", referral.id, side),
+            now,
+        )
+        .await?;
+        sessions.extend(
+            credits::grant(
+                tx,
+                user_id,
+                bonus,
+                CreditSource::Referral,
+                billing.credit_expiry(CreditSource::Referral, now),
+                now,
+            )
+            .await?,
+        );
+    }
+    metrics::counter!("referrals_rewarded_total").increment(1);
+    Ok(sessions)
+}
@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+
+use crate::{config::refill::RefillConfig, entity::session};
+
+/// When the free credit refill is next due for `session`, or `None` if the
+/// user doesn't qualify for refills.
+pub fn next_refill_at(
+    config: &RefillConfig,
+    session: &session::Model,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    if !config.is_enabled() || (config.non_subscribers_only && session.subscription_status) {
+        return None;
+    }
+    let due = session.last_refill_at.unwrap_or(session.created_at) + config.period;
+    Some(due.max(now))
+}
+
+/// Credits a refill adds to `balance`: up to the configured amount, without
+/// going past the cap.
+pub fn refill_amount(config: &RefillConfig, balance: i64) -> i64 {
+    config.amount.min(config.cap - balance).max(0)
+}
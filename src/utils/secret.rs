@@ -62,3 +62,8 @@ pub async fn verify_signature(
         .run(Request::from_parts(parts, whole_body.into()))
         .await)
 }
+
+/// Compares two secrets without leaking the position of the first mismatch.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -3,23 +3,44 @@ use std::fmt::Display;
 use std::sync::Arc;
 use std::time::Duration;
 
-use sea_orm::TransactionTrait;
+use chrono::Utc;
+use rand::Rng;
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use tracing::{info, warn};
 
 use crate::{
-    client::redis::{RedisClient, RedisClientExt},
-    entity::session,
-    repositories, ServiceState,
+    client::cache::CacheClient,
+    entity::{credit_bucket, session},
+    repositories,
+    utils::quota,
+    ServiceState,
 };
 
+/// A typed cache key.
+///
+/// Keys scoped to a single user wrap the user id in a `{hash tag}` so that all
+/// of that user's keys map to the same slot when Redis runs in cluster mode.
 pub trait RedisKey: Debug + Display {
     type Value: Serialize + DeserializeOwned + Debug;
     const EXPIRE_TIME: Duration;
     fn expire(&self) -> Duration {
         Self::EXPIRE_TIME
     }
+    /// Version of `value` used to reject out-of-order cache writes. Keys that
+    /// return `None` are written unconditionally.
+    fn version(_value: &Self::Value) -> Option<i64> {
+        None
+    }
+}
+
+/// What actually gets stored under a key: the value plus the version it was
+/// written at, if the key type is versioned.
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry<V> {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<i64>,
+    value: V,
 }
 
 #[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone)]
@@ -30,87 +51,347 @@ pub struct SessionKey {
 impl RedisKey for SessionKey {
     type Value = session::Model;
     const EXPIRE_TIME: Duration = Duration::from_secs(600);
+    fn version(value: &Self::Value) -> Option<i64> {
+        Some(value.version)
+    }
 }
 
 impl Display for SessionKey {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "SESSION_KEY_{}", self.user_id)
+        write!(f, "SESSION_KEY_{{{}}}", self.user_id)
+    }
+}
+
+/// Marks a user id that has no session, so repeated lookups of unknown users
+/// are answered from the cache instead of Postgres.
+#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone)]
+pub struct MissingSessionKey {
+    pub user_id: i64,
+}
+
+impl RedisKey for MissingSessionKey {
+    type Value = bool;
+    const EXPIRE_TIME: Duration = Duration::from_secs(30);
+}
+
+impl Display for MissingSessionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "MISSING_SESSION_KEY_{{{}}}", self.user_id)
+    }
+}
+
+/// The parts of the session response that live outside the session row,
+/// valid for the session `version` they were read at.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SessionBreakdown {
+    pub version: i64,
+    pub tier: String,
+    pub credit_buckets: Vec<credit_bucket::Model>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Ord, PartialOrd, Eq, PartialEq, Clone)]
+pub struct SessionBreakdownKey {
+    pub user_id: i64,
+}
+
+impl RedisKey for SessionBreakdownKey {
+    type Value = SessionBreakdown;
+    const EXPIRE_TIME: Duration = Duration::from_secs(600);
+    fn version(value: &Self::Value) -> Option<i64> {
+        Some(value.version)
     }
 }
 
-pub async fn set<K>(client: &RedisClient, (key, value): (&K, &K::Value)) -> Result<(), String>
+impl Display for SessionBreakdownKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SESSION_BREAKDOWN_KEY_{{{}}}", self.user_id)
+    }
+}
+
+pub async fn set<K>(client: &CacheClient, (key, value): (&K, &K::Value)) -> Result<(), String>
+where
+    K: RedisKey,
+{
+    set_with_expire(client, (key, value), key.expire()).await
+}
+
+pub async fn set_with_expire<K>(
+    client: &CacheClient,
+    (key, value): (&K, &K::Value),
+    expire: Duration,
+) -> Result<(), String>
 where
     K: RedisKey,
 {
     info!("Set value to redis key :{key:?} value :{value:?}");
-    let value =
-        serde_json::to_string(value).map_err(|e| format!("serde to_string error: {}", e))?;
-    client
-        .set(&key.to_string(), &value, K::EXPIRE_TIME)
-        .await
-        .map_err(|e| format!("Redis client set error: {}", e))?;
+    let version = K::version(value);
+    let entry = serde_json::to_string(&CacheEntry { version, value })
+        .map_err(|e| format!("serde to_string error: {}", e))?;
+    match version {
+        Some(version) => {
+            if !client
+                .set_if_newer(&key.to_string(), &entry, version, expire)
+                .await
+                .map_err(|e| format!("Cache client set error: {}", e))?
+            {
+                info!("Skipped stale cache write for key :{key:?} version :{version}");
+            }
+        }
+        None => client
+            .set(&key.to_string(), &entry, expire)
+            .await
+            .map_err(|e| format!("Cache client set error: {}", e))?,
+    }
     Ok(())
 }
 
-pub async fn get<K>(client: &RedisClient, key: &K) -> Result<Option<K::Value>, String>
+pub async fn get<K>(client: &CacheClient, key: &K) -> Result<Option<K::Value>, String>
 where
     K: RedisKey,
 {
-    Ok(client
+    let Some(raw) = client
         .get(&key.to_string())
         .await
-        .map_err(|e| format!("Redis client get error: {}", e))?
-        .map(|v| serde_json::from_str::<K::Value>(&v))
-        .transpose()
-        .map_err(|e| format!("Redis transpose error: {}", e))?)
+        .map_err(|e| format!("Cache client get error: {}", e))?
+    else {
+        return Ok(None);
+    };
+    match serde_json::from_str::<CacheEntry<K::Value>>(&raw) {
+        Ok(entry) => Ok(Some(entry.value)),
+        Err(e) => {
+            // Entries in an older format are treated as a miss and rewritten.
+            warn!("Ignoring undecodable cache entry for key :{key:?}: {}", e);
+            Ok(None)
+        }
+    }
+}
+/// Post-commit hook body: writes the committed value to the shared cache, or
+/// evicts the key if the write fails so readers fall back to the database,
+/// then drops the key from every instance's local cache.
+pub async fn write_after_commit<K>(
+    state: Arc<ServiceState>,
+    key: K,
+    value: K::Value,
+) -> Result<(), String>
+where
+    K: RedisKey,
+{
+    let cache = state.cache.as_ref();
+    if let Err(e) = set(cache, (&key, &value)).await {
+        warn!(
+            "Cache write for key :{key:?} failed, evicting instead: {}",
+            e
+        );
+        del(cache, &key).await?;
+    }
+    state.invalidation.invalidate(&key).await
 }
-pub async fn del(client: &RedisClient, key: &impl RedisKey) -> Result<bool, String> {
+
+pub async fn del(client: &CacheClient, key: &impl RedisKey) -> Result<bool, String> {
     client
         .del(&key.to_string())
         .await
-        .map_err(|e| format!("Redis client del error: {}", e))
+        .map_err(|e| format!("Cache client del error: {}", e))
 }
 
-pub async fn check_exist_key(redis: &RedisClient, key: &impl RedisKey) -> Result<bool, String> {
-    Ok(redis
+pub async fn check_exist_key(client: &CacheClient, key: &impl RedisKey) -> Result<bool, String> {
+    client
         .exist(&key.to_string())
         .await
-        .map_err(|e| format!("Redis client check existing error: {}", e))?)
+        .map_err(|e| format!("Cache client check existing error: {}", e))
+}
+
+/// Shortens `expire` by a random fraction of up to `jitter`, so entries written
+/// together don't all expire in the same instant.
+pub fn jittered(expire: Duration, jitter: f64) -> Duration {
+    if jitter <= 0.0 {
+        return expire;
+    }
+    expire.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..jitter))
+}
+
+/// Decides whether a cache hit should be refreshed ahead of its expiry. The
+/// chance grows linearly from zero to one across the final `window` of the TTL.
+async fn should_refresh_early(
+    client: &CacheClient,
+    key: &impl RedisKey,
+    window: Duration,
+) -> Result<bool, String> {
+    if window.is_zero() {
+        return Ok(false);
+    }
+    let ttl = client
+        .ttl(&key.to_string())
+        .await
+        .map_err(|e| format!("Cache client ttl error: {}", e))?;
+    if ttl < 0 {
+        return Ok(false);
+    }
+    let remaining = Duration::from_secs(ttl as u64);
+    if remaining >= window {
+        return Ok(false);
+    }
+    let probability = 1.0 - remaining.as_secs_f64() / window.as_secs_f64();
+    Ok(rand::thread_rng().gen_bool(probability.clamp(0.0, 1.0)))
 }
 
 pub async fn get_session_by_user_id(
     state: Arc<ServiceState>,
     user_id: i64,
 ) -> Result<session::Model, String> {
-    let transaction = state
-        .db
-        .begin()
+    let cache = state.cache.as_ref();
+    let session_key = SessionKey { user_id };
+    let missing_key = MissingSessionKey { user_id };
+
+    let local_ttl = state.config.cache.session_local_ttl;
+    if !local_ttl.is_zero() {
+        if let Some(model) = get(state.invalidation.local(), &session_key).await? {
+            return Ok(model);
+        }
+    }
+    let generation = state.invalidation.generation();
+
+    if let Some(model) = get(cache, &session_key)
         .await
-        .map_err(|e| format!("Failed to start a database transaction: {}", e))?;
-    let session_key = SessionKey { user_id: user_id };
-    match get(&state.redis, &session_key)
+        .map_err(|e| format!("Failed to get session from cache: {}", e))?
+    {
+        let window = state.config.cache.session_early_refresh_window;
+        if !should_refresh_early(cache, &session_key, window).await? {
+            cache_locally(&state, generation, &session_key, &model).await?;
+            return Ok(model);
+        }
+        // Only one caller refreshes; everyone else keeps serving the cached value.
+        let Some(_flight) = state.session_loads.try_acquire(&session_key.to_string()) else {
+            return Ok(model);
+        };
+        info!("Refreshing session for user ID {} ahead of expiry", user_id);
+        return match load_session(&state, generation, user_id).await {
+            Ok(model) => Ok(model),
+            Err(e) => {
+                warn!("Early session refresh failed, serving cached value: {}", e);
+                Ok(model)
+            }
+        };
+    }
+
+    if check_exist_key(cache, &missing_key).await? {
+        return Err("Session data not found".to_string());
+    }
+
+    let _flight = state.session_loads.acquire(&session_key.to_string()).await;
+
+    // Another caller may have filled the cache while we were waiting.
+    if let Some(model) = get(cache, &session_key)
         .await
-        .map_err(|e| format!("Failed to get session from Redis: {}", e))?
+        .map_err(|e| format!("Failed to get session from cache: {}", e))?
     {
-        Some(model) => Ok(model),
-        None => {
-            let session_data = repositories::session::find_by_user_id(&transaction, user_id)
-                .await
-                .map_err(|e| format!("Failed to find session by ID: {}", e))?;
+        cache_locally(&state, generation, &session_key, &model).await?;
+        return Ok(model);
+    }
+    if check_exist_key(cache, &missing_key).await? {
+        return Err("Session data not found".to_string());
+    }
 
-            if session_data.is_none() {
-                return Err("Session data not found".to_string());
-            }
-            transaction
-                .commit()
-                .await
-                .map_err(|e| format!("Failed to commit transaction: {}", e))?;
-            let session_data = session_data.unwrap();
-            set(&state.redis, (&session_key, &session_data))
-                .await
-                .map_err(|e| format!("Failed to set session in Redis: {}", e))?;
+    load_session(&state, generation, user_id).await
+}
 
-            return Ok(session_data);
-        }
+/// Fills the cache from the primary. A lagging replica could otherwise cache a
+/// session that was just created as missing, or a balance or status that was
+/// just changed, for the whole TTL.
+async fn load_session(
+    state: &ServiceState,
+    generation: u64,
+    user_id: i64,
+) -> Result<session::Model, String> {
+    let session_data = repositories::session::find_by_user_id(state.db.as_ref(), user_id)
+        .await
+        .map_err(|e| format!("Failed to find session by ID: {}", e))?;
+
+    let cache = state.cache.as_ref();
+    let Some(session_data) = session_data else {
+        set(cache, (&MissingSessionKey { user_id }, &true))
+            .await
+            .map_err(|e| format!("Failed to set missing session marker in cache: {}", e))?;
+        return Err("Session data not found".to_string());
+    };
+
+    let session_key = SessionKey { user_id };
+    set_with_expire(
+        cache,
+        (&session_key, &session_data),
+        jittered(session_key.expire(), state.config.cache.session_ttl_jitter),
+    )
+    .await
+    .map_err(|e| format!("Failed to set session in cache: {}", e))?;
+    cache_locally(state, generation, &session_key, &session_data).await?;
+
+    Ok(session_data)
+}
+
+/// Keeps `model`, read at invalidation `generation`, in the local cache. If
+/// the session was evicted while it was being read the entry could be stale,
+/// so it is dropped again and the next read goes to the shared cache.
+async fn cache_locally(
+    state: &ServiceState,
+    generation: u64,
+    key: &SessionKey,
+    model: &session::Model,
+) -> Result<(), String> {
+    let local_ttl = state.config.cache.session_local_ttl;
+    if local_ttl.is_zero() {
+        return Ok(());
+    }
+    let local = state.invalidation.local();
+    set_with_expire(local, (key, model), local_ttl).await?;
+    if !state.invalidation.is_current(generation) {
+        del(local, key).await?;
+    }
+    Ok(())
+}
+
+/// Credit buckets and quota tier for `session`. Every change to either bumps
+/// the session version, so a cached breakdown at the session's version is
+/// current and reads only go to the primary once per version.
+pub async fn get_breakdown(
+    state: &ServiceState,
+    session: &session::Model,
+) -> Result<SessionBreakdown, String> {
+    let cache = state.cache.as_ref();
+    let key = SessionBreakdownKey {
+        user_id: session.user_id,
+    };
+    let now = Utc::now();
+    let cached = get(cache, &key).await.unwrap_or_else(|e| {
+        warn!("Failed to get session breakdown from cache: {}", e);
+        None
+    });
+    if let Some(mut breakdown) = cached.filter(|breakdown| breakdown.version == session.version) {
+        // Buckets run out on their own before the expiry job catches up.
+        breakdown
+            .credit_buckets
+            .retain(|bucket| bucket.expires_at.is_none_or(|expires_at| expires_at > now));
+        return Ok(breakdown);
+    }
+
+    let credit_buckets = repositories::credit_bucket::find_spendable_by_user_id(
+        state.db.as_ref(),
+        session.user_id,
+        now,
+    )
+    .await?;
+    let tier = quota::tier_for(
+        state.db.as_ref(),
+        session.user_id,
+        session.subscription_status,
+    )
+    .await?;
+    let breakdown = SessionBreakdown {
+        version: session.version,
+        tier,
+        credit_buckets,
+    };
+    if let Err(e) = set(cache, (&key, &breakdown)).await {
+        warn!("Failed to set session breakdown in cache: {}", e);
     }
+    Ok(breakdown)
 }
@@ -0,0 +1,40 @@
+use chrono::Utc;
+use sea_orm::DatabaseTransaction;
+use uuid::Uuid;
+
+use crate::{
+    config::ServiceConfig, entity::credit_bucket::CreditSource, repositories, utils::credits,
+};
+
+/// Creates the user and session rows for a first-time user from the signup
+/// defaults for `start_param`, so the two rows always start out agreeing.
+/// Returns the session ID.
+pub async fn create_account(
+    tx: &DatabaseTransaction,
+    config: &ServiceConfig,
+    user_id: i64,
+    username: Option<String>,
+    start_param: Option<&str>,
+) -> Result<Uuid, String> {
+    let now = Utc::now();
+    let defaults = config.signup.resolve(start_param);
+    repositories::user::save(tx, user_id, username, defaults.credits).await?;
+    let session_id = repositories::session::save(
+        tx,
+        user_id,
+        defaults.credits,
+        defaults.preferences,
+        defaults.session_metadata,
+    )
+    .await?;
+    credits::add_bucket(
+        tx,
+        user_id,
+        defaults.credits,
+        CreditSource::Signup,
+        config.billing.credit_expiry(CreditSource::Signup, now),
+        now,
+    )
+    .await?;
+    Ok(session_id)
+}
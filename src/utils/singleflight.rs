@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard};
+
+/// Coalesces concurrent loads of the same key, so only one caller goes to the
+/// database while the others wait for its result to land in the cache.
+#[derive(Debug, Default)]
+pub struct SingleFlight {
+    locks: Mutex<HashMap<String, Arc<AsyncMutex<()>>>>,
+}
+
+/// Held by the caller currently loading a key. Dropping it lets the next
+/// waiter in and forgets the key once nobody is waiting on it any more.
+pub struct Flight<'a> {
+    owner: &'a SingleFlight,
+    key: String,
+    guard: Option<OwnedMutexGuard<()>>,
+}
+
+impl SingleFlight {
+    fn lock_for(&self, key: &str) -> Arc<AsyncMutex<()>> {
+        self.locks
+            .lock()
+            .unwrap()
+            .entry(key.to_string())
+            .or_default()
+            .clone()
+    }
+
+    /// Waits until no other caller is loading `key`.
+    pub async fn acquire(&self, key: &str) -> Flight<'_> {
+        let guard = self.lock_for(key).lock_owned().await;
+        Flight {
+            owner: self,
+            key: key.to_string(),
+            guard: Some(guard),
+        }
+    }
+
+    /// Returns `None` straight away if another caller is already loading `key`.
+    pub fn try_acquire(&self, key: &str) -> Option<Flight<'_>> {
+        let guard = self.lock_for(key).try_lock_owned().ok()?;
+        Some(Flight {
+            owner: self,
+            key: key.to_string(),
+            guard: Some(guard),
+        })
+    }
+
+    fn forget_if_idle(&self, key: &str) {
+        let mut locks = self.locks.lock().unwrap();
+        if locks
+            .get(key)
+            .is_some_and(|lock| Arc::strong_count(lock) == 1)
+        {
+            locks.remove(key);
+        }
+    }
+}
+
+impl Drop for Flight<'_> {
+    fn drop(&mut self) {
+        drop(self.guard.take());
+        self.owner.forget_if_idle(&self.key);
+    }
+}
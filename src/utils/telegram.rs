@@ -0,0 +1,33 @@
+use std::sync::Arc;
+
+use crate::utils::secret::constant_time_eq;
+use crate::ServiceState;
+use axum::extract::State;
+use axum::{extract::Request, http::StatusCode, middleware::Next, response::IntoResponse};
+use tracing::error;
+
+pub const SECRET_TOKEN_HEADER: &str = "X-Telegram-Bot-Api-Secret-Token";
+
+pub async fn verify_webhook_secret(
+    State(state): State<Arc<ServiceState>>,
+    req: Request,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let Some(expected) = state.config.telegram.webhook_secret.as_deref() else {
+        let error_message = "Telegram webhook is not configured".to_string();
+        error!("{}", error_message);
+        return Err((StatusCode::SERVICE_UNAVAILABLE, error_message));
+    };
+    let provided = req
+        .headers()
+        .get(SECRET_TOKEN_HEADER)
+        .map(|value| value.as_bytes())
+        .unwrap_or_default();
+    if !constant_time_eq(provided, expected.as_bytes()) {
+        let error_message = format!("Missing or invalid '{}' header", SECRET_TOKEN_HEADER);
+        error!("{}", error_message);
+        return Err((StatusCode::UNAUTHORIZED, error_message));
+    }
+
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,56 @@
+use std::future::Future;
+use std::ops::Deref;
+use std::pin::Pin;
+
+use sea_orm::{DatabaseConnection, DatabaseTransaction, DbErr, TransactionTrait};
+use tracing::error;
+
+type PostCommitHook = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// A database transaction with work deferred until after it commits.
+///
+/// Cache writes belong in `after_commit` hooks: they only run once the data
+/// they describe is durable, and they are dropped if the transaction is
+/// rolled back or never committed.
+pub struct UnitOfWork {
+    transaction: DatabaseTransaction,
+    hooks: Vec<(&'static str, PostCommitHook)>,
+}
+
+impl UnitOfWork {
+    pub async fn begin(db: &DatabaseConnection) -> Result<Self, DbErr> {
+        Ok(Self {
+            transaction: db.begin().await?,
+            hooks: Vec::new(),
+        })
+    }
+
+    pub fn after_commit<F>(&mut self, name: &'static str, hook: F)
+    where
+        F: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        self.hooks.push((name, Box::pin(hook)));
+    }
+
+    /// Commits the transaction, then runs the hooks in registration order.
+    /// Hook failures are logged rather than returned: the data is already
+    /// committed at that point and the caller can't undo it.
+    pub async fn commit(self) -> Result<(), DbErr> {
+        self.transaction.commit().await?;
+        for (name, hook) in self.hooks {
+            if let Err(e) = hook.await {
+                metrics::counter!("post_commit_hook_failures_total", "hook" => name).increment(1);
+                error!("Post-commit hook '{}' failed: {}", name, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Deref for UnitOfWork {
+    type Target = DatabaseTransaction;
+
+    fn deref(&self) -> &Self::Target {
+        &self.transaction
+    }
+}